@@ -0,0 +1,86 @@
+//! Compares tracing rays one voxel at a time against skipping the empty space that `World::occupancy` knows about
+//!
+//! Run with `cargo bench --bench ray_traversal`
+
+use criterion::{criterion_group, criterion_main, BenchmarkId, Criterion};
+use tesseracts::{
+    get_intersection, get_intersection_unaccelerated, Camera, Ray, TerrainGenerator,
+    TerrainMaterials, Voxel, World,
+};
+
+const WIDTH: u32 = 48;
+const HEIGHT: u32 = 32;
+const MAX_DISTANCE: f32 = 256.0;
+
+/// The rays of a small image from `camera`
+fn camera_rays(camera: &Camera) -> Vec<Ray> {
+    let aspect = WIDTH as f32 / HEIGHT as f32;
+    (0..HEIGHT)
+        .flat_map(|y| (0..WIDTH).map(move |x| (x, y)))
+        .map(|(x, y)| {
+            let normalized_uv = cgmath::vec2(
+                x as f32 / WIDTH as f32 * 2.0 - 1.0,
+                y as f32 / HEIGHT as f32 * 2.0 - 1.0,
+            );
+            camera.ray(normalized_uv.extend(0.0), aspect)
+        })
+        .collect()
+}
+
+/// Terrain seen from just above the ground, where most rays cross a lot of sky
+fn terrain() -> (World, Camera) {
+    let mut world = World::new();
+    let generator = TerrainGenerator::new(3, TerrainMaterials::add_to(&mut world));
+    generator.fill(
+        &mut world,
+        cgmath::vec4(-8, -4, -2, -1),
+        cgmath::vec4(7, 3, 13, 0),
+    );
+    let camera = Camera {
+        position: cgmath::vec4(0.5, generator.height(0, 0, 0) as f32 + 3.5, 0.5, 0.5),
+        ..Default::default()
+    };
+    (world, camera)
+}
+
+/// A few voxels spread far apart, where nearly everything is empty
+fn sparse() -> (World, Camera) {
+    let mut world = World::new();
+    for i in -4..=4 {
+        for j in -4..=4 {
+            world.set_voxel(cgmath::vec4(i * 16, j * 16, 120, 0), Voxel::new(0));
+        }
+    }
+    (world, Camera::default())
+}
+
+fn ray_traversal(c: &mut Criterion) {
+    let mut group = c.benchmark_group("ray_traversal");
+    for (name, (world, camera)) in [("terrain", terrain()), ("sparse", sparse())] {
+        let rays = camera_rays(&camera);
+        group.bench_with_input(
+            BenchmarkId::new("voxel_by_voxel", name),
+            &rays,
+            |b, rays| {
+                b.iter(|| {
+                    rays.iter()
+                        .filter_map(|&ray| {
+                            get_intersection_unaccelerated(&world, ray, MAX_DISTANCE)
+                        })
+                        .count()
+                })
+            },
+        );
+        group.bench_with_input(BenchmarkId::new("skipping", name), &rays, |b, rays| {
+            b.iter(|| {
+                rays.iter()
+                    .filter_map(|&ray| get_intersection(&world, ray, MAX_DISTANCE))
+                    .count()
+            })
+        });
+    }
+    group.finish();
+}
+
+criterion_group!(benches, ray_traversal);
+criterion_main!(benches);
@@ -1,257 +1,91 @@
-use eframe::{
-    egui, egui_wgpu,
-    wgpu::{self, include_wgsl},
-};
-use encase::{ArrayLength, ShaderSize, ShaderType, UniformBuffer};
-
-use crate::{StorageBuffer, Texture, CHUNK_SIZE};
-
-#[derive(ShaderType)]
-pub struct GpuCamera {
-    position: cgmath::Vector4<f32>,
-    forward: cgmath::Vector4<f32>,
-    right: cgmath::Vector4<f32>,
-    up: cgmath::Vector4<f32>,
-    fov: f32,
-    max_distance: f32,
-}
-
-#[derive(ShaderType)]
-pub struct Material {
-    color: cgmath::Vector3<f32>,
-}
+use eframe::{egui, egui_wgpu, wgpu};
 
-#[derive(ShaderType)]
-pub struct Materials<'a> {
-    count: ArrayLength,
-    #[size(runtime)]
-    data: &'a [Material],
-}
-
-#[derive(ShaderType)]
-pub struct Voxel {
-    material: u32,
-}
+use crate::{
+    get_intersection, load_world, save_world, Camera, ChunkStreamer, FovAxis, Hit, Projection,
+    ProjectionMode, RenderMode, Renderer, RotationPlane, Voxel, World, MAX_BOUNCES,
+};
 
-#[derive(ShaderType)]
-pub struct Chunk {
-    data: [Voxel; (CHUNK_SIZE * CHUNK_SIZE * CHUNK_SIZE * CHUNK_SIZE) as _],
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum FileAction {
+    Save,
+    Open,
 }
 
 pub struct App {
     last_time: std::time::Instant,
     info_window: bool,
-    main_texture: Texture<'static>,
+    render_mode: RenderMode,
+    renderer: Renderer,
     main_egui_texture_id: egui::TextureId,
-    main_texture_bind_group_layout: wgpu::BindGroupLayout,
-    main_texture_bind_group: wgpu::BindGroup,
-    camera: GpuCamera,
-    camera_uniform_buffer: wgpu::Buffer,
-    camera_bind_group: wgpu::BindGroup,
-    materials_storage_buffer: StorageBuffer<'static>,
-    voxels_storage_buffer: StorageBuffer<'static>,
-    tesseracts_bind_group_layout: wgpu::BindGroupLayout,
-    tesseracts_bind_group: wgpu::BindGroup,
-    ray_tracing_pipeline: wgpu::ComputePipeline,
+    camera: Camera,
+    world: World,
+    selected_material: u32,
+    target: Option<Hit>,
+    chunk_streamer: Option<ChunkStreamer>,
+    file_action: Option<FileAction>,
+    file_path: String,
+    file_error: Option<String>,
 }
 
 impl App {
-    pub fn new(cc: &eframe::CreationContext<'_>) -> Self {
+    /// `chunk_streamer` keeps `world` filled in around the camera, if there is one
+    pub fn new(
+        cc: &eframe::CreationContext<'_>,
+        world: World,
+        camera: Camera,
+        chunk_streamer: Option<ChunkStreamer>,
+    ) -> Self {
         let egui_wgpu::RenderState {
             device, renderer, ..
         } = cc.wgpu_render_state.as_ref().unwrap();
 
-        let main_texture = Texture::new(
-            device,
-            wgpu::TextureDescriptor {
-                label: Some("Main Texture"),
-                size: wgpu::Extent3d {
-                    width: 1,
-                    height: 1,
-                    depth_or_array_layers: 1,
-                },
-                mip_level_count: 1,
-                sample_count: 1,
-                dimension: wgpu::TextureDimension::D2,
-                format: wgpu::TextureFormat::Rgba8Unorm,
-                usage: wgpu::TextureUsages::TEXTURE_BINDING | wgpu::TextureUsages::STORAGE_BINDING,
-                view_formats: &[],
-            },
-        );
+        let tesseracts_renderer = Renderer::new(device);
         let main_egui_texture_id = renderer.write().register_native_texture(
             device,
-            &main_texture.create_view(&Default::default()),
+            &tesseracts_renderer
+                .main_texture()
+                .create_view(&Default::default()),
             wgpu::FilterMode::Nearest,
         );
 
-        let main_texture_bind_group_layout =
-            device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
-                label: Some("Main Texture Bind Group Layout"),
-                entries: &[wgpu::BindGroupLayoutEntry {
-                    binding: 0,
-                    visibility: wgpu::ShaderStages::COMPUTE,
-                    ty: wgpu::BindingType::StorageTexture {
-                        access: wgpu::StorageTextureAccess::WriteOnly,
-                        format: wgpu::TextureFormat::Rgba8Unorm,
-                        view_dimension: wgpu::TextureViewDimension::D2,
-                    },
-                    count: None,
-                }],
-            });
-        let main_texture_bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
-            label: Some("Main Texture Bind Group"),
-            layout: &main_texture_bind_group_layout,
-            entries: &[wgpu::BindGroupEntry {
-                binding: 0,
-                resource: wgpu::BindingResource::TextureView(
-                    &main_texture.create_view(&Default::default()),
-                ),
-            }],
-        });
-
-        let camera_uniform_buffer = device.create_buffer(&wgpu::BufferDescriptor {
-            label: Some("Camera Uniform Buffer"),
-            size: <GpuCamera as ShaderSize>::SHADER_SIZE.get(),
-            usage: wgpu::BufferUsages::COPY_DST | wgpu::BufferUsages::UNIFORM,
-            mapped_at_creation: false,
-        });
-        let camera_bind_group_layout =
-            device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
-                label: Some("Camera Bind Group Layout"),
-                entries: &[wgpu::BindGroupLayoutEntry {
-                    binding: 0,
-                    visibility: wgpu::ShaderStages::COMPUTE,
-                    ty: wgpu::BindingType::Buffer {
-                        ty: wgpu::BufferBindingType::Uniform,
-                        has_dynamic_offset: false,
-                        min_binding_size: Some(<GpuCamera as ShaderSize>::SHADER_SIZE),
-                    },
-                    count: None,
-                }],
-            });
-        let camera_bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
-            label: Some("Camera Bind Group"),
-            layout: &camera_bind_group_layout,
-            entries: &[wgpu::BindGroupEntry {
-                binding: 0,
-                resource: wgpu::BindingResource::Buffer(wgpu::BufferBinding {
-                    buffer: &camera_uniform_buffer,
-                    offset: 0,
-                    size: Some(<GpuCamera as ShaderSize>::SHADER_SIZE),
-                }),
-            }],
-        });
-
-        let materials_storage_buffer = StorageBuffer::new(
-            device,
-            wgpu::BufferDescriptor {
-                label: Some("Materials Storage Buffer"),
-                size: <Materials<'_> as ShaderType>::min_size().get(),
-                usage: wgpu::BufferUsages::COPY_DST | wgpu::BufferUsages::STORAGE,
-                mapped_at_creation: false,
-            },
-        );
-        let voxels_storage_buffer = StorageBuffer::new(
-            device,
-            wgpu::BufferDescriptor {
-                label: Some("Voxels Storage Buffer"),
-                size: <Chunk as ShaderType>::min_size().get(),
-                usage: wgpu::BufferUsages::COPY_DST | wgpu::BufferUsages::STORAGE,
-                mapped_at_creation: false,
-            },
-        );
-        let tesseracts_bind_group_layout =
-            device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
-                label: Some("Tesseracts Bind Group Layout"),
-                entries: &[
-                    wgpu::BindGroupLayoutEntry {
-                        binding: 0,
-                        visibility: wgpu::ShaderStages::COMPUTE,
-                        ty: wgpu::BindingType::Buffer {
-                            ty: wgpu::BufferBindingType::Storage { read_only: true },
-                            has_dynamic_offset: false,
-                            min_binding_size: Some(<Materials<'_> as ShaderType>::min_size()),
-                        },
-                        count: None,
-                    },
-                    wgpu::BindGroupLayoutEntry {
-                        binding: 1,
-                        visibility: wgpu::ShaderStages::COMPUTE,
-                        ty: wgpu::BindingType::Buffer {
-                            ty: wgpu::BufferBindingType::Storage { read_only: true },
-                            has_dynamic_offset: false,
-                            min_binding_size: Some(<Chunk as ShaderType>::min_size()),
-                        },
-                        count: None,
-                    },
-                ],
-            });
-        let tesseracts_bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
-            label: Some("Tesseracts Bind Group"),
-            layout: &tesseracts_bind_group_layout,
-            entries: &[
-                wgpu::BindGroupEntry {
-                    binding: 0,
-                    resource: wgpu::BindingResource::Buffer(wgpu::BufferBinding {
-                        buffer: &materials_storage_buffer,
-                        offset: 0,
-                        size: None,
-                    }),
-                },
-                wgpu::BindGroupEntry {
-                    binding: 1,
-                    resource: wgpu::BindingResource::Buffer(wgpu::BufferBinding {
-                        buffer: &voxels_storage_buffer,
-                        offset: 0,
-                        size: None,
-                    }),
-                },
-            ],
-        });
-
-        let ray_tracing_shader = device.create_shader_module(include_wgsl!("./ray_tracing.wgsl"));
-        let ray_tracing_pipeline_layout =
-            device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
-                label: Some("Ray Tracing Pipeline Layout"),
-                bind_group_layouts: &[
-                    &main_texture_bind_group_layout,
-                    &camera_bind_group_layout,
-                    &tesseracts_bind_group_layout,
-                ],
-                push_constant_ranges: &[],
-            });
-        let ray_tracing_pipeline =
-            device.create_compute_pipeline(&wgpu::ComputePipelineDescriptor {
-                label: Some("Ray Tracing Pipeline"),
-                layout: Some(&ray_tracing_pipeline_layout),
-                module: &ray_tracing_shader,
-                entry_point: "main",
-            });
-
         Self {
             last_time: std::time::Instant::now(),
             info_window: false,
-            main_texture,
+            render_mode: RenderMode::Perspective,
+            renderer: tesseracts_renderer,
             main_egui_texture_id,
-            main_texture_bind_group_layout,
-            main_texture_bind_group,
-            camera: GpuCamera {
-                position: cgmath::vec4(0.0, 0.0, -3.0, 0.0),
-                forward: cgmath::vec4(0.001, 0.0, 1.0, 0.0),
-                right: cgmath::vec4(1.0, 0.0, -0.001, 0.0),
-                up: cgmath::vec4(0.0, 1.0, 0.0, 0.0),
-                fov: 90.0,
-                max_distance: 100.0,
-            },
-            camera_uniform_buffer,
-            camera_bind_group,
-            materials_storage_buffer,
-            voxels_storage_buffer,
-            tesseracts_bind_group_layout,
-            tesseracts_bind_group,
-            ray_tracing_pipeline,
+            camera,
+            world,
+            selected_material: 0,
+            target: None,
+            chunk_streamer,
+            file_action: None,
+            file_path: "world.tesseracts".into(),
+            file_error: None,
         }
     }
+
+    pub fn world(&self) -> &World {
+        &self.world
+    }
+
+    pub fn world_mut(&mut self) -> &mut World {
+        &mut self.world
+    }
+
+    fn save_file(&self) -> std::io::Result<()> {
+        let mut writer = std::io::BufWriter::new(std::fs::File::create(&self.file_path)?);
+        save_world(&mut writer, &self.world)?;
+        std::io::Write::flush(&mut writer)
+    }
+
+    fn open_file(&mut self) -> std::io::Result<()> {
+        let mut reader = std::io::BufReader::new(std::fs::File::open(&self.file_path)?);
+        self.world = load_world(&mut reader)?;
+        // the loaded world is complete, so streaming more chunks into it would just overwrite it
+        self.chunk_streamer = None;
+        Ok(())
+    }
 }
 
 impl eframe::App for App {
@@ -271,33 +105,212 @@ impl eframe::App for App {
 
         if !ctx.wants_keyboard_input() {
             ctx.input(|i| {
+                let forward = self.camera.forward();
+                let right = self.camera.right();
+                let up = self.camera.up();
+                let ana = self.camera.ana();
                 if i.key_down(egui::Key::W) {
-                    self.camera.position += self.camera.forward * (5.0 * ts);
+                    self.camera.position += forward * (5.0 * ts);
                 }
                 if i.key_down(egui::Key::S) {
-                    self.camera.position -= self.camera.forward * (5.0 * ts);
+                    self.camera.position -= forward * (5.0 * ts);
                 }
                 if i.key_down(egui::Key::A) {
-                    self.camera.position -= self.camera.right * (5.0 * ts);
+                    self.camera.position -= right * (5.0 * ts);
                 }
                 if i.key_down(egui::Key::D) {
-                    self.camera.position += self.camera.right * (5.0 * ts);
+                    self.camera.position += right * (5.0 * ts);
                 }
                 if i.key_down(egui::Key::Q) {
-                    self.camera.position -= self.camera.up * (5.0 * ts);
+                    self.camera.position -= up * (5.0 * ts);
                 }
                 if i.key_down(egui::Key::E) {
-                    self.camera.position += self.camera.up * (5.0 * ts);
+                    self.camera.position += up * (5.0 * ts);
+                }
+                if i.key_down(egui::Key::F) {
+                    self.camera.position -= ana * (5.0 * ts);
+                }
+                if i.key_down(egui::Key::R) {
+                    self.camera.position += ana * (5.0 * ts);
+                }
+
+                let rotation_keys = [
+                    (
+                        RotationPlane::XZ,
+                        egui::Key::ArrowRight,
+                        egui::Key::ArrowLeft,
+                    ),
+                    (RotationPlane::YZ, egui::Key::ArrowUp, egui::Key::ArrowDown),
+                    (RotationPlane::XY, egui::Key::C, egui::Key::Z),
+                    (RotationPlane::XW, egui::Key::L, egui::Key::J),
+                    (RotationPlane::YW, egui::Key::O, egui::Key::U),
+                    (RotationPlane::ZW, egui::Key::I, egui::Key::K),
+                ];
+                for (plane, positive, negative) in rotation_keys {
+                    if i.key_down(positive) {
+                        self.camera.rotate(plane, 2.0 * ts);
+                    }
+                    if i.key_down(negative) {
+                        self.camera.rotate(plane, -2.0 * ts);
+                    }
                 }
             });
         }
 
         egui::TopBottomPanel::top("Menu").show(ctx, |ui| {
             ui.horizontal(|ui| {
+                ui.menu_button("File", |ui| {
+                    if ui.button("Open…").clicked() {
+                        self.file_action = Some(FileAction::Open);
+                        self.file_error = None;
+                        ui.close_menu();
+                    }
+                    if ui.button("Save…").clicked() {
+                        self.file_action = Some(FileAction::Save);
+                        self.file_error = None;
+                        ui.close_menu();
+                    }
+                });
                 self.info_window |= ui.button("Info").clicked();
+                ui.separator();
+                ui.selectable_value(
+                    &mut self.render_mode,
+                    RenderMode::Perspective,
+                    "Perspective",
+                );
+                ui.selectable_value(&mut self.render_mode, RenderMode::Slice, "Slice");
+                ui.selectable_value(&mut self.render_mode, RenderMode::Volumetric, "Volumetric");
+                ui.selectable_value(&mut self.render_mode, RenderMode::PathTraced, "Path Traced");
+                if self.render_mode == RenderMode::PathTraced {
+                    ui.label(format!("{} samples", self.renderer.samples()));
+                }
+                ui.separator();
+                ui.menu_button("Projection", |ui| {
+                    let projection = &mut self.camera.projection;
+                    ui.radio_value(
+                        &mut projection.mode,
+                        ProjectionMode::Perspective,
+                        "Perspective",
+                    );
+                    ui.radio_value(
+                        &mut projection.mode,
+                        ProjectionMode::Orthographic,
+                        "Orthographic",
+                    );
+                    ui.separator();
+                    ui.radio_value(&mut projection.fov_axis, FovAxis::Vertical, "Vertical");
+                    ui.radio_value(&mut projection.fov_axis, FovAxis::Horizontal, "Horizontal");
+                    ui.separator();
+                    match projection.mode {
+                        ProjectionMode::Perspective => {
+                            ui.add(
+                                egui::Slider::new(
+                                    &mut projection.fov.0,
+                                    Projection::MIN_FOV.0..=Projection::MAX_FOV.0,
+                                )
+                                .text("FOV")
+                                .suffix("°"),
+                            );
+                        }
+                        ProjectionMode::Orthographic => {
+                            ui.add(
+                                egui::Slider::new(&mut projection.orthographic_size, 0.5..=50.0)
+                                    .logarithmic(true)
+                                    .text("Size"),
+                            );
+                        }
+                    }
+                });
+                ui.separator();
+                egui::ComboBox::from_label("Material")
+                    .selected_text(format!("Material {}", self.selected_material))
+                    .show_ui(ui, |ui| {
+                        for (id, material) in self.world.materials().iter().enumerate() {
+                            let color = egui::Rgba::from_rgb(
+                                material.color.x,
+                                material.color.y,
+                                material.color.z,
+                            );
+                            ui.horizontal(|ui| {
+                                let (rect, _) = ui.allocate_exact_size(
+                                    egui::vec2(12.0, 12.0),
+                                    egui::Sense::hover(),
+                                );
+                                ui.painter().rect_filled(rect, 2.0, color);
+                                ui.selectable_value(
+                                    &mut self.selected_material,
+                                    id as u32,
+                                    format!("Material {id}"),
+                                );
+                            });
+                        }
+                    });
+                if let Some(&material) = self.world.materials().get(self.selected_material as usize)
+                {
+                    ui.menu_button("Edit Material", |ui| {
+                        let mut material = material;
+                        let color: &mut [f32; 3] = material.color.as_mut();
+                        ui.horizontal(|ui| {
+                            ui.color_edit_button_rgb(color);
+                            ui.label("Color");
+                        });
+                        ui.add(
+                            egui::Slider::new(&mut material.reflectivity, 0.0..=1.0)
+                                .text("Reflectivity"),
+                        );
+                        ui.add(
+                            egui::Slider::new(&mut material.transparency, 0.0..=1.0)
+                                .text("Transparency"),
+                        );
+                        ui.add_enabled(
+                            material.transparency > 0.0,
+                            egui::Slider::new(&mut material.refractive_index, 1.0..=3.0)
+                                .text("Refractive Index"),
+                        );
+                        ui.add(
+                            egui::Slider::new(&mut material.emission, 0.0..=4.0).text("Emission"),
+                        );
+                        self.world.set_material(self.selected_material, material);
+                    });
+                }
             });
         });
 
+        if let Some(action) = self.file_action {
+            let mut open = true;
+            let mut done = false;
+            let (title, button) = match action {
+                FileAction::Save => ("Save World", "Save"),
+                FileAction::Open => ("Open World", "Open"),
+            };
+            egui::Window::new(title)
+                .open(&mut open)
+                .collapsible(false)
+                .resizable(false)
+                .show(ctx, |ui| {
+                    ui.horizontal(|ui| {
+                        ui.label("Path");
+                        ui.text_edit_singleline(&mut self.file_path);
+                        if ui.button(button).clicked() {
+                            let result = match action {
+                                FileAction::Save => self.save_file(),
+                                FileAction::Open => self.open_file(),
+                            };
+                            match result {
+                                Ok(()) => done = true,
+                                Err(error) => self.file_error = Some(error.to_string()),
+                            }
+                        }
+                    });
+                    if let Some(error) = &self.file_error {
+                        ui.colored_label(egui::Color32::RED, error);
+                    }
+                });
+            if !open || done {
+                self.file_action = None;
+            }
+        }
+
         egui::Window::new("Info")
             .open(&mut self.info_window)
             .default_size((150.0, 1.0))
@@ -305,6 +318,78 @@ impl eframe::App for App {
             .show(ctx, |ui| {
                 ui.label(format!("FPS: {:.3}", 1.0 / ts));
                 ui.label(format!("Frame Time: {:.3}ms", ts * 1000.0));
+                ui.label(format!("Chunks: {}", self.world.chunks().count()));
+                if let Some(chunk_streamer) = &mut self.chunk_streamer {
+                    ui.label(format!(
+                        "Pending Chunks: {}",
+                        chunk_streamer.pending_count()
+                    ));
+                    ui.add(
+                        egui::Slider::new(&mut chunk_streamer.radius, 0.0..=8.0)
+                            .text("View Distance"),
+                    );
+                }
+                ui.collapsing("Lighting", |ui| {
+                    let lighting = &mut self.renderer.lighting;
+                    ui.horizontal(|ui| {
+                        ui.label("Sun Direction");
+                        let components: &mut [f32; 4] = lighting.sun_direction.as_mut();
+                        for (axis, component) in ["X", "Y", "Z", "W"].into_iter().zip(components) {
+                            ui.add(
+                                egui::DragValue::new(component)
+                                    .speed(0.01)
+                                    .clamp_range(-1.0..=1.0)
+                                    .prefix(format!("{axis} ")),
+                            );
+                        }
+                    });
+                    if ui.button("Sun Behind Camera").clicked() {
+                        lighting.sun_direction = -self.camera.forward();
+                    }
+                    ui.add(
+                        egui::Slider::new(&mut lighting.sun_intensity, 0.0..=2.0)
+                            .text("Sun Intensity"),
+                    );
+                    ui.add(egui::Slider::new(&mut lighting.ambient, 0.0..=1.0).text("Ambient"));
+                    ui.add(
+                        egui::Slider::new(&mut lighting.max_bounces, 0..=MAX_BOUNCES)
+                            .text("Max Bounces"),
+                    );
+                    ui.add(
+                        egui::Slider::new(&mut lighting.block_light, 0.0..=2.0).text("Block Light"),
+                    );
+                    ui.checkbox(&mut lighting.shadows, "Shadows");
+                    ui.checkbox(&mut lighting.ambient_occlusion, "Ambient Occlusion");
+                    ui.add_enabled(
+                        lighting.ambient_occlusion,
+                        egui::Slider::new(&mut lighting.ambient_occlusion_strength, 0.0..=1.0)
+                            .text("Occlusion Strength"),
+                    );
+                });
+                ui.collapsing("Environment", |ui| {
+                    let environment = &mut self.renderer.environment;
+                    for (label, color) in [
+                        ("Zenith", &mut environment.zenith_color),
+                        ("Horizon", &mut environment.horizon_color),
+                        ("Ground", &mut environment.ground_color),
+                        ("Sun Disc", &mut environment.sun_disc_color),
+                    ] {
+                        ui.horizontal(|ui| {
+                            ui.color_edit_button_rgb(color.as_mut());
+                            ui.label(label);
+                        });
+                    }
+                    ui.add(
+                        egui::Slider::new(&mut environment.sun_disc_radius.0, 0.0..=10.0)
+                            .text("Sun Disc Radius"),
+                    );
+                    ui.checkbox(&mut environment.fog, "Fog");
+                    ui.add_enabled(
+                        environment.fog,
+                        egui::Slider::new(&mut environment.fog_density, 0.0..=0.5)
+                            .text("Fog Density"),
+                    );
+                });
                 ui.allocate_space(ui.available_size());
             });
 
@@ -313,167 +398,80 @@ impl eframe::App for App {
             .show(ctx, |ui| {
                 let size = ui.available_size();
 
-                // Update bind group and egui texture id if it has changed size
-                if self.main_texture.resize(
-                    device,
-                    cgmath::vec2(size.x.max(1.0) as _, size.y.max(1.0) as _),
-                ) {
-                    self.main_texture_bind_group =
-                        device.create_bind_group(&wgpu::BindGroupDescriptor {
-                            label: Some("Main Texture Bind Group"),
-                            layout: &self.main_texture_bind_group_layout,
-                            entries: &[wgpu::BindGroupEntry {
-                                binding: 0,
-                                resource: wgpu::BindingResource::TextureView(
-                                    &self.main_texture.create_view(&Default::default()),
-                                ),
-                            }],
-                        });
+                // Update egui texture id if it has changed size
+                if self
+                    .renderer
+                    .resize(device, cgmath::vec2(size.x as _, size.y as _))
+                {
                     renderer.write().update_egui_texture_from_wgpu_texture(
                         device,
-                        &self.main_texture.create_view(&Default::default()),
+                        &self
+                            .renderer
+                            .main_texture()
+                            .create_view(&Default::default()),
                         wgpu::FilterMode::Nearest,
                         self.main_egui_texture_id,
                     );
                 }
 
-                // Upload camera
-                {
-                    let mut uniform_buffer =
-                        UniformBuffer::new([0; <GpuCamera as ShaderSize>::SHADER_SIZE.get() as _]);
-                    uniform_buffer.write(&self.camera).unwrap();
-                    let buffer = uniform_buffer.into_inner();
-                    queue.write_buffer(&self.camera_uniform_buffer, 0, &buffer);
+                self.camera.orthonormalize();
+                if let Some(chunk_streamer) = &mut self.chunk_streamer {
+                    chunk_streamer.update(&mut self.world, self.camera.position);
                 }
+                let view_camera = self.render_mode.view_camera(&self.camera);
 
-                // Upload materials and voxels
-                {
-                    let materials = Materials {
-                        count: ArrayLength,
-                        data: &[
-                            Material {
-                                color: cgmath::Vector3 {
-                                    x: 1.0,
-                                    y: 0.0,
-                                    z: 0.0,
-                                },
-                            },
-                            Material {
-                                color: cgmath::Vector3 {
-                                    x: 0.0,
-                                    y: 1.0,
-                                    z: 0.0,
-                                },
-                            },
-                            Material {
-                                color: cgmath::Vector3 {
-                                    x: 0.0,
-                                    y: 0.0,
-                                    z: 1.0,
-                                },
-                            },
-                        ],
-                    };
-
-                    let mut chunk = Chunk {
-                        data: std::array::from_fn(|_| Voxel { material: u32::MAX }),
-                    };
-                    chunk.data[0].material = 0;
-                    chunk.data[2].material = 1;
-                    chunk.data[4].material = 2;
+                // Pick the voxel in the center of the screen
+                self.target = get_intersection(
+                    &self.world,
+                    view_camera.ray(cgmath::vec3(0.0, 0.0, 0.0), size.x / size.y),
+                    view_camera.max_distance,
+                );
 
-                    let mut bind_group_invalidated = false;
+                self.renderer.render(
+                    device,
+                    queue,
+                    &mut self.world,
+                    &view_camera,
+                    self.render_mode,
+                    self.target.map(|hit| hit.voxel_position),
+                );
+
+                let response = ui.add(
+                    egui::Image::new(self.main_egui_texture_id, size)
+                        .sense(egui::Sense::click_and_drag()),
+                );
+                if response.dragged_by(egui::PointerButton::Primary) {
+                    let delta = response.drag_delta() * 0.005;
+                    self.camera.rotate(RotationPlane::XZ, delta.x);
+                    self.camera.rotate(RotationPlane::YZ, -delta.y);
+                }
 
-                    // Upload materials
+                // Break and place blocks
+                if let Some(target) = self.target {
+                    if response.clicked() {
+                        self.world.set_voxel(target.voxel_position, Voxel::EMPTY);
+                    } else if response.secondary_clicked()
+                        && (self.selected_material as usize) < self.world.materials().len()
                     {
-                        let mut materials_storage_buffer =
-                            encase::StorageBuffer::new(Vec::with_capacity(
-                                std::mem::size_of_val(&materials.count)
-                                    + std::mem::size_of_val(materials.data),
-                            ));
-                        materials_storage_buffer.write(&materials).unwrap();
-                        let materials_buffer = materials_storage_buffer.into_inner();
-
-                        bind_group_invalidated |= self.materials_storage_buffer.set_data_lossy(
-                            device,
-                            queue,
-                            &materials_buffer,
-                        );
-                    }
-
-                    // Upload chunks
-                    {
-                        let mut chunk_storage_buffer = encase::StorageBuffer::new(
-                            Vec::with_capacity(std::mem::size_of_val(&chunk.data)),
-                        );
-                        chunk_storage_buffer.write(&chunk).unwrap();
-                        let chunk_buffer = chunk_storage_buffer.into_inner();
-                        bind_group_invalidated |=
-                            self.voxels_storage_buffer
-                                .set_data_lossy(device, queue, &chunk_buffer);
-                    }
-
-                    if bind_group_invalidated {
-                        self.tesseracts_bind_group =
-                            device.create_bind_group(&wgpu::BindGroupDescriptor {
-                                label: Some("Tesseracts Bind Group"),
-                                layout: &self.tesseracts_bind_group_layout,
-                                entries: &[
-                                    wgpu::BindGroupEntry {
-                                        binding: 0,
-                                        resource: wgpu::BindingResource::Buffer(
-                                            wgpu::BufferBinding {
-                                                buffer: &self.materials_storage_buffer,
-                                                offset: 0,
-                                                size: None,
-                                            },
-                                        ),
-                                    },
-                                    wgpu::BindGroupEntry {
-                                        binding: 1,
-                                        resource: wgpu::BindingResource::Buffer(
-                                            wgpu::BufferBinding {
-                                                buffer: &self.voxels_storage_buffer,
-                                                offset: 0,
-                                                size: None,
-                                            },
-                                        ),
-                                    },
-                                ],
-                            });
+                        let position = target.voxel_position + target.normal.map(|x| x as i32);
+                        self.world
+                            .set_voxel(position, Voxel::new(self.selected_material));
                     }
                 }
 
-                // Submit ray tracing commands
+                // Crosshair
                 {
-                    let mut command_encoder =
-                        device.create_command_encoder(&wgpu::CommandEncoderDescriptor {
-                            label: Some("Ray Tracing Encoder"),
-                        });
-                    // Compute Pass
-                    {
-                        let mut compute_pass =
-                            command_encoder.begin_compute_pass(&wgpu::ComputePassDescriptor {
-                                label: Some("Ray Tracing Compute Pass"),
-                            });
-                        compute_pass.set_pipeline(&self.ray_tracing_pipeline);
-                        compute_pass.set_bind_group(0, &self.main_texture_bind_group, &[]);
-                        compute_pass.set_bind_group(1, &self.camera_bind_group, &[]);
-                        compute_pass.set_bind_group(2, &self.tesseracts_bind_group, &[]);
-                        let workgroups = {
-                            const WORKGROUPS_SIZE: cgmath::Vector2<u32> = cgmath::vec2(16, 16);
-                            let size = self.main_texture.size();
-                            cgmath::vec2(
-                                (size.width + WORKGROUPS_SIZE.x - 1) / WORKGROUPS_SIZE.x,
-                                (size.height + WORKGROUPS_SIZE.y - 1) / WORKGROUPS_SIZE.y,
-                            )
-                        };
-                        compute_pass.dispatch_workgroups(workgroups.x, workgroups.y, 1);
-                    }
-                    queue.submit([command_encoder.finish()]);
+                    let center = response.rect.center();
+                    let stroke = egui::Stroke::new(2.0, egui::Color32::WHITE);
+                    ui.painter().line_segment(
+                        [center - egui::vec2(8.0, 0.0), center + egui::vec2(8.0, 0.0)],
+                        stroke,
+                    );
+                    ui.painter().line_segment(
+                        [center - egui::vec2(0.0, 8.0), center + egui::vec2(0.0, 8.0)],
+                        stroke,
+                    );
                 }
-
-                ui.image(self.main_egui_texture_id, size);
             });
 
         ctx.request_repaint();
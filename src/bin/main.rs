@@ -1,8 +1,56 @@
 use eframe::{run_native, wgpu};
 use std::sync::Arc;
-use tesseracts::App;
+use tesseracts::{App, Camera, ChunkStreamer, Scene, TerrainGenerator, TerrainMaterials, World};
+
+const USAGE: &str = "\
+Usage: tesseracts [options]
+
+Options:
+    --scene <path>   load the world and camera from a RON scene file
+    --seed <seed>    stream in terrain generated from this seed around the camera";
+
+/// How far away chunks are loaded when streaming terrain, in chunks
+const VIEW_DISTANCE: f32 = 4.0;
+
+fn main() -> Result<(), Box<dyn std::error::Error>> {
+    let mut world = World::example();
+    let mut camera = Camera::default();
+    let mut chunk_streamer = None;
+
+    let mut args = std::env::args().skip(1);
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--scene" => {
+                let path = args
+                    .next()
+                    .ok_or_else(|| format!("missing value for `{arg}`\n\n{USAGE}"))?;
+                let scene = Scene::load(&path)
+                    .map_err(|error| format!("failed to load `{path}`: {error}"))?;
+                world = scene.world()?;
+                camera = scene.camera.camera();
+                chunk_streamer = None;
+            }
+            "--seed" => {
+                let seed = args
+                    .next()
+                    .ok_or_else(|| format!("missing value for `{arg}`\n\n{USAGE}"))?
+                    .parse()?;
+                world = World::new();
+                let generator = TerrainGenerator::new(seed, TerrainMaterials::add_to(&mut world));
+                camera = Camera {
+                    position: cgmath::vec4(0.5, generator.height(0, 0, 0) as f32 + 2.5, 0.5, 0.5),
+                    ..Default::default()
+                };
+                chunk_streamer = Some(ChunkStreamer::new(generator, VIEW_DISTANCE));
+            }
+            "--help" => {
+                println!("{USAGE}");
+                return Ok(());
+            }
+            _ => return Err(format!("unknown argument `{arg}`\n\n{USAGE}").into()),
+        }
+    }
 
-fn main() -> Result<(), eframe::Error> {
     run_native(
         "4D Game",
         eframe::NativeOptions {
@@ -19,6 +67,7 @@ fn main() -> Result<(), eframe::Error> {
             },
             ..Default::default()
         },
-        Box::new(|cc| Box::new(App::new(cc))),
-    )
+        Box::new(move |cc| Box::new(App::new(cc, world, camera, chunk_streamer))),
+    )?;
+    Ok(())
 }
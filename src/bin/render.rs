@@ -0,0 +1,136 @@
+use tesseracts::{
+    render_cpu, save_png, Camera, Environment, Lighting, ProjectionMode, Scene, TerrainGenerator,
+    TerrainMaterials, World,
+};
+
+const USAGE: &str = "\
+Renders the world on the CPU and writes it to a PNG
+
+Usage: render [options]
+
+Options:
+    --scene <path>         load the world and camera from a RON scene file,
+                           instead of the red, green and blue voxels
+    --seed <seed>          generate terrain around the origin from this seed instead
+    --output <path>        where to write the image (default: render.png)
+    --width <pixels>       (default: 640)
+    --height <pixels>      (default: 480)
+    --position <x,y,z,w>   the position of the camera
+    --fov <degrees>        the vertical field of view (default: 90)
+    --orthographic <size>  use an orthographic projection this many units tall
+    --slice                render the 3D cross section at the camera's W position
+    --sun <x,y,z,w>        the direction towards the sun
+    --ambient <amount>     how much light reaches faces facing away from the sun (default: 0.35)
+    --no-shadows           let sunlight through voxels
+    --bounces <count>      how many times rays are reflected and refracted (default: 4)
+    --occlusion <amount>   how much light fully occluded faces lose, 0 to turn ambient occlusion off (default: 0.6)
+    --fog <density>        fade hits towards the sky with distance, losing this much light per unit";
+
+fn parse_vector4(s: &str) -> Result<cgmath::Vector4<f32>, String> {
+    let components = s
+        .split(',')
+        .map(|component| component.trim().parse::<f32>())
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|error| format!("invalid vector `{s}`: {error}"))?;
+    match components[..] {
+        [x, y, z, w] => Ok(cgmath::vec4(x, y, z, w)),
+        _ => Err(format!("expected 4 components, but got `{s}`")),
+    }
+}
+
+/// A width or height, which has to be at least one pixel
+fn parse_size(s: &str) -> Result<u32, String> {
+    match s.parse::<u32>() {
+        Ok(0) => Err(format!(
+            "the image can't be 0 pixels wide or tall\n\n{USAGE}"
+        )),
+        Ok(size) => Ok(size),
+        Err(error) => Err(format!("invalid size `{s}`: {error}")),
+    }
+}
+
+fn main() -> Result<(), Box<dyn std::error::Error>> {
+    let mut output = std::path::PathBuf::from("render.png");
+    let mut width = 640;
+    let mut height = 480;
+    let mut scene = None;
+    let mut seed = None;
+    let mut position = None;
+    let mut fov = None;
+    let mut orthographic_size = None;
+    let mut slice = false;
+    let mut lighting = Lighting::default();
+    let mut environment = Environment::default();
+
+    let mut args = std::env::args().skip(1);
+    while let Some(arg) = args.next() {
+        let mut value = || {
+            args.next()
+                .ok_or_else(|| format!("missing value for `{arg}`\n\n{USAGE}"))
+        };
+        match arg.as_str() {
+            "--scene" => scene = Some(Scene::load(value()?)?),
+            "--seed" => seed = Some(value()?.parse()?),
+            "--output" => output = value()?.into(),
+            "--width" => width = parse_size(&value()?)?,
+            "--height" => height = parse_size(&value()?)?,
+            "--position" => position = Some(parse_vector4(&value()?)?),
+            "--fov" => fov = Some(value()?.parse()?),
+            "--orthographic" => orthographic_size = Some(value()?.parse()?),
+            "--slice" => slice = true,
+            "--sun" => lighting.sun_direction = parse_vector4(&value()?)?,
+            "--ambient" => lighting.ambient = value()?.parse()?,
+            "--no-shadows" => lighting.shadows = false,
+            "--bounces" => lighting.max_bounces = value()?.parse()?,
+            "--occlusion" => {
+                lighting.ambient_occlusion_strength = value()?.parse()?;
+                lighting.ambient_occlusion = lighting.ambient_occlusion_strength > 0.0;
+            }
+            "--fog" => {
+                environment.fog = true;
+                environment.fog_density = value()?.parse()?;
+            }
+            "--help" => {
+                println!("{USAGE}");
+                return Ok(());
+            }
+            _ => return Err(format!("unknown argument `{arg}`\n\n{USAGE}").into()),
+        }
+    }
+
+    let (world, mut camera) = match (scene, seed) {
+        (Some(scene), _) => (scene.world()?, scene.camera.camera()),
+        (None, Some(seed)) => {
+            let mut world = World::new();
+            let generator = TerrainGenerator::new(seed, TerrainMaterials::add_to(&mut world));
+            generator.fill(
+                &mut world,
+                cgmath::vec4(-6, -4, -6, -1),
+                cgmath::vec4(5, 3, 5, 0),
+            );
+            let camera = Camera {
+                position: cgmath::vec4(0.5, generator.height(0, 0, 0) as f32 + 2.5, 0.5, 0.5),
+                ..Default::default()
+            };
+            (world, camera)
+        }
+        (None, None) => (World::example(), Camera::default()),
+    };
+    if let Some(position) = position {
+        camera.position = position;
+    }
+    if let Some(fov) = fov {
+        camera.projection.fov = cgmath::Deg(fov);
+    }
+    if let Some(orthographic_size) = orthographic_size {
+        camera.projection.mode = ProjectionMode::Orthographic;
+        camera.projection.orthographic_size = orthographic_size;
+    }
+    if slice {
+        camera = camera.slice();
+    }
+
+    let image = render_cpu(&world, &camera, &lighting, &environment, width, height);
+    save_png(&output, width, height, &image)?;
+    Ok(())
+}
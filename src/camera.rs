@@ -0,0 +1,239 @@
+use cgmath::{InnerSpace, Matrix, SquareMatrix};
+
+use crate::{FovAxis, Projection, ProjectionMode, Ray};
+use encase::ShaderType;
+use serde::{Deserialize, Serialize};
+
+/// How far the dot products of the axes can be from those of an orthonormal basis
+/// before `Camera::orthonormalize` does anything
+const ORTHONORMAL_TOLERANCE: f32 = 1e-5;
+
+#[derive(ShaderType, Clone, Copy, Debug, PartialEq)]
+pub struct GpuCamera {
+    position: cgmath::Vector4<f32>,
+    forward: cgmath::Vector4<f32>,
+    right: cgmath::Vector4<f32>,
+    up: cgmath::Vector4<f32>,
+    ana: cgmath::Vector4<f32>,
+    half_extent: f32,
+    fov_axis: u32,
+    orthographic: u32,
+    max_distance: f32,
+}
+
+/// The 4D analogue of the cross product, returns the vector orthogonal to all three inputs,
+/// oriented so that `cross(x, y, z) == w`
+pub fn cross(
+    a: cgmath::Vector4<f32>,
+    b: cgmath::Vector4<f32>,
+    c: cgmath::Vector4<f32>,
+) -> cgmath::Vector4<f32> {
+    let det3 = |i: usize, j: usize, k: usize| {
+        cgmath::Matrix3::new(a[i], b[i], c[i], a[j], b[j], c[j], a[k], b[k], c[k]).determinant()
+    };
+    cgmath::vec4(-det3(1, 2, 3), det3(0, 2, 3), -det3(0, 1, 3), det3(0, 1, 2))
+}
+
+/// A plane of rotation, named by the camera's local axes
+/// where X is right, Y is up, Z is forward and W is ana
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum RotationPlane {
+    XY,
+    XZ,
+    XW,
+    YZ,
+    YW,
+    ZW,
+}
+
+impl RotationPlane {
+    /// The indices of the two axes spanning the plane
+    pub fn axes(self) -> (usize, usize) {
+        match self {
+            Self::XY => (0, 1),
+            Self::XZ => (0, 2),
+            Self::XW => (0, 3),
+            Self::YZ => (1, 2),
+            Self::YW => (1, 3),
+            Self::ZW => (2, 3),
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug)]
+pub struct Camera {
+    pub position: cgmath::Vector4<f32>,
+    /// The columns are the right, up, forward and ana axes of the camera
+    pub orientation: cgmath::Matrix4<f32>,
+    pub projection: Projection,
+    pub max_distance: f32,
+}
+
+impl Camera {
+    pub fn right(&self) -> cgmath::Vector4<f32> {
+        self.orientation.x
+    }
+
+    pub fn up(&self) -> cgmath::Vector4<f32> {
+        self.orientation.y
+    }
+
+    pub fn forward(&self) -> cgmath::Vector4<f32> {
+        self.orientation.z
+    }
+
+    pub fn ana(&self) -> cgmath::Vector4<f32> {
+        self.orientation.w
+    }
+
+    /// Rotates the camera in one of its local planes,
+    /// a positive angle turns the first axis of the plane away from the second
+    /// (so `XZ` turns forward towards right)
+    pub fn rotate(&mut self, plane: RotationPlane, angle: f32) {
+        let (a, b) = plane.axes();
+        let (sin, cos) = angle.sin_cos();
+        let axis_a = self.orientation[a];
+        let axis_b = self.orientation[b];
+        self.orientation[a] = axis_a * cos - axis_b * sin;
+        self.orientation[b] = axis_b * cos + axis_a * sin;
+    }
+
+    /// Removes the drift that accumulates from repeated rotations,
+    /// keeping the forward axis as the most stable one
+    ///
+    /// The ana axis is always derived from the other three,
+    /// so it is only used to carry rotations in the planes involving W between calls.
+    /// Axes that are already orthonormal are left exactly as they are,
+    /// so that calling this every frame doesn't make a still camera look like it moved.
+    pub fn orthonormalize(&mut self) {
+        let gram = self.orientation.transpose() * self.orientation;
+        if (0..4).all(|i| {
+            (0..4).all(|j| {
+                let expected = if i == j { 1.0 } else { 0.0 };
+                (gram[i][j] - expected).abs() <= ORTHONORMAL_TOLERANCE
+            })
+        }) {
+            return;
+        }
+
+        let forward = self.orientation.z.normalize();
+        let up = (self.orientation.y - forward * forward.dot(self.orientation.y)).normalize();
+        let right = (self.orientation.x
+            - forward * forward.dot(self.orientation.x)
+            - up * up.dot(self.orientation.x))
+        .normalize();
+        let ana = cross(right, up, forward);
+        self.orientation = cgmath::Matrix4::from_cols(right, up, forward, ana);
+    }
+
+    /// The ray through a point on the screen, the same as `get_camera_ray` in `ray_tracing.wgsl`
+    ///
+    /// `normalized_coords` are in the range -1 to 1, with z going along the ana axis
+    /// and spanning the same extent as the up axis
+    pub fn ray(&self, normalized_coords: cgmath::Vector3<f32>, aspect: f32) -> Ray {
+        let half_extents = self.projection.half_extents(aspect);
+        let offset = self.right() * (normalized_coords.x * half_extents.x)
+            + self.up() * (normalized_coords.y * half_extents.y)
+            + self.ana() * (normalized_coords.z * half_extents.y);
+        match self.projection.mode {
+            ProjectionMode::Perspective => Ray {
+                origin: self.position,
+                direction: (offset + self.forward()).normalize(),
+            },
+            ProjectionMode::Orthographic => Ray {
+                origin: self.position + offset,
+                direction: self.forward(),
+            },
+        }
+    }
+
+    /// The camera restricted to the 3D hyperplane of the world at its current W position,
+    /// looking along the parts of its axes that lie within that hyperplane
+    pub fn slice(&self) -> Camera {
+        const EPSILON: f32 = 0.001;
+
+        let flatten = |v: cgmath::Vector4<f32>| cgmath::vec4(v.x, v.y, v.z, 0.0);
+        let first_orthogonal =
+            |against: &[cgmath::Vector4<f32>], candidates: &[cgmath::Vector4<f32>]| {
+                candidates
+                    .iter()
+                    .map(|&candidate| {
+                        against
+                            .iter()
+                            .fold(candidate, |v, &axis| v - axis * axis.dot(v))
+                    })
+                    .find(|v| v.magnitude() > EPSILON)
+                    .unwrap()
+                    .normalize()
+            };
+
+        // when looking straight along W the ana axis is guaranteed to lie mostly within the hyperplane
+        let forward = first_orthogonal(
+            &[],
+            &[
+                flatten(self.forward()),
+                flatten(self.ana()),
+                cgmath::Vector4::unit_z(),
+            ],
+        );
+        let up = first_orthogonal(
+            &[forward],
+            &[
+                flatten(self.up()),
+                flatten(self.ana()),
+                flatten(self.right()),
+                cgmath::Vector4::unit_y(),
+                cgmath::Vector4::unit_x(),
+            ],
+        );
+        let right = first_orthogonal(
+            &[forward, up],
+            &[
+                flatten(self.right()),
+                flatten(self.ana()),
+                cgmath::Vector4::unit_x(),
+                cgmath::Vector4::unit_y(),
+                cgmath::Vector4::unit_z(),
+            ],
+        );
+        // keep the slice right handed, even if the projection of the original axes was mirrored
+        let right = if cross(right, up, forward).w < 0.0 {
+            -right
+        } else {
+            right
+        };
+
+        Camera {
+            orientation: cgmath::Matrix4::from_cols(right, up, forward, cross(right, up, forward)),
+            ..*self
+        }
+    }
+
+    pub fn gpu(&self) -> GpuCamera {
+        GpuCamera {
+            position: self.position,
+            forward: self.forward(),
+            right: self.right(),
+            up: self.up(),
+            ana: self.ana(),
+            half_extent: self.projection.half_extent(),
+            fov_axis: match self.projection.fov_axis {
+                FovAxis::Vertical => 0,
+                FovAxis::Horizontal => 1,
+            },
+            orthographic: (self.projection.mode == ProjectionMode::Orthographic) as u32,
+            max_distance: self.max_distance,
+        }
+    }
+}
+
+impl Default for Camera {
+    fn default() -> Self {
+        Self {
+            position: cgmath::vec4(0.0, 0.0, -3.0, 0.0),
+            orientation: cgmath::Matrix4::identity(),
+            projection: Projection::default(),
+            max_distance: 100.0,
+        }
+    }
+}
@@ -0,0 +1,227 @@
+use eframe::wgpu;
+use encase::{ShaderSize, ShaderType};
+use std::collections::{BTreeSet, HashMap};
+use std::ops::Range;
+
+use crate::{Chunk, StorageBuffer, Voxel};
+
+/// The size of one chunk in the voxel pool
+pub const CHUNK_SLOT_SIZE: wgpu::BufferAddress = <Chunk as ShaderSize>::SHADER_SIZE.get();
+const VOXEL_SIZE: usize = <Voxel as ShaderSize>::SHADER_SIZE.get() as usize;
+/// The pool isn't compacted until it has at least this many free slots, so small worlds never move chunks around
+const MIN_COMPACTION_FREE_SLOTS: usize = 64;
+
+/// What `ChunkPool::compact` did
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Compaction {
+    /// The chunks whose slots changed
+    pub moved: Vec<cgmath::Vector4<i32>>,
+    /// Whether the buffer was reallocated to a smaller size
+    pub reallocated: bool,
+}
+
+/// Hands out fixed size slots for the voxels of chunks within one storage buffer
+///
+/// Slots of removed chunks go on a free list and are handed out lowest first.
+/// The buffer only grows or shrinks by doubling or halving, keeping its contents,
+/// so anything bound to it only has to be recreated when `reserve` or `compact` say it was reallocated.
+pub struct ChunkPool {
+    storage_buffer: StorageBuffer<'static>,
+    slots: HashMap<cgmath::Vector4<i32>, u32>,
+    /// The chunk in each slot, up to the highest slot that has been handed out
+    owners: Vec<Option<cgmath::Vector4<i32>>>,
+    free_slots: BTreeSet<u32>,
+}
+
+impl ChunkPool {
+    pub fn new(device: &wgpu::Device) -> Self {
+        let storage_buffer = StorageBuffer::new(
+            device,
+            wgpu::BufferDescriptor {
+                label: Some("Voxels Storage Buffer"),
+                size: <Chunk as ShaderType>::min_size().get(),
+                usage: wgpu::BufferUsages::COPY_SRC
+                    | wgpu::BufferUsages::COPY_DST
+                    | wgpu::BufferUsages::STORAGE,
+                mapped_at_creation: false,
+            },
+        );
+        Self {
+            storage_buffer,
+            slots: HashMap::new(),
+            owners: Vec::new(),
+            free_slots: BTreeSet::new(),
+        }
+    }
+
+    pub fn buffer(&self) -> &wgpu::Buffer {
+        &self.storage_buffer
+    }
+
+    /// The number of chunks in the pool
+    pub fn len(&self) -> usize {
+        self.slots.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.slots.is_empty()
+    }
+
+    /// How many slots fit in the buffer
+    pub fn capacity(&self) -> u32 {
+        (self.storage_buffer.buffer_descriptor.size / CHUNK_SLOT_SIZE) as u32
+    }
+
+    /// The number of slots up to the highest one in use, including free ones in between
+    pub fn used_slots(&self) -> u32 {
+        self.owners.len() as u32
+    }
+
+    pub fn slot(&self, chunk_position: cgmath::Vector4<i32>) -> Option<u32> {
+        self.slots.get(&chunk_position).copied()
+    }
+
+    pub fn slots(&self) -> impl Iterator<Item = (cgmath::Vector4<i32>, u32)> + '_ {
+        self.slots.iter().map(|(&position, &slot)| (position, slot))
+    }
+
+    /// Frees every slot, without touching the buffer
+    pub fn clear(&mut self) {
+        self.slots.clear();
+        self.owners.clear();
+        self.free_slots.clear();
+    }
+
+    /// Hands out the lowest free slot to the chunk, which mustn't have one already
+    ///
+    /// The slot may be beyond the capacity of the buffer until `reserve` is called
+    pub fn allocate(&mut self, chunk_position: cgmath::Vector4<i32>) -> u32 {
+        assert!(
+            !self.slots.contains_key(&chunk_position),
+            "chunk {chunk_position:?} already has a slot"
+        );
+        let slot = self.free_slots.pop_first().unwrap_or_else(|| {
+            self.owners.push(None);
+            self.owners.len() as u32 - 1
+        });
+        self.owners[slot as usize] = Some(chunk_position);
+        self.slots.insert(chunk_position, slot);
+        slot
+    }
+
+    /// Returns the slot of the chunk to the free list, returning which slot it was
+    pub fn free(&mut self, chunk_position: cgmath::Vector4<i32>) -> Option<u32> {
+        let slot = self.slots.remove(&chunk_position)?;
+        self.owners[slot as usize] = None;
+        self.free_slots.insert(slot);
+        // free slots at the end are forgotten about, so they aren't counted towards fragmentation
+        while self.owners.last() == Some(&None) {
+            self.owners.pop();
+            self.free_slots.remove(&(self.owners.len() as u32));
+        }
+        Some(slot)
+    }
+
+    /// Grows the buffer so that every allocated slot fits, keeping its contents
+    ///
+    /// Returns whether the buffer was reallocated
+    pub fn reserve(&mut self, device: &wgpu::Device, queue: &wgpu::Queue) -> bool {
+        let capacity = self.capacity();
+        if self.used_slots() <= capacity {
+            return false;
+        }
+        let capacity = self.used_slots().max(capacity * 2);
+        self.storage_buffer
+            .grow(device, queue, capacity as u64 * CHUNK_SLOT_SIZE)
+    }
+
+    /// Uploads `range` of the voxels of `chunk` into the slot of the chunk at `chunk_position`
+    pub fn write(
+        &self,
+        queue: &wgpu::Queue,
+        chunk_position: cgmath::Vector4<i32>,
+        range: Range<usize>,
+        chunk: &Chunk,
+    ) {
+        // the same layout as `Chunk` in the shader
+        let data = chunk.data[range.clone()]
+            .iter()
+            .flat_map(|voxel| [voxel.material, voxel.light])
+            .flat_map(u32::to_le_bytes)
+            .collect::<Vec<_>>();
+        self.storage_buffer.write_range(
+            queue,
+            self.slots[&chunk_position] as u64 * CHUNK_SLOT_SIZE
+                + (range.start * VOXEL_SIZE) as u64,
+            &data,
+        );
+    }
+
+    /// Whether enough of the used slots are free that compacting is worth it
+    pub fn needs_compaction(&self) -> bool {
+        self.free_slots.len() >= MIN_COMPACTION_FREE_SLOTS && self.free_slots.len() > self.len()
+    }
+
+    /// Moves the chunks in the highest slots into the free slots below them, so that the used slots have no gaps,
+    /// and halves the buffer while it's less than a quarter full
+    pub fn compact(&mut self, device: &wgpu::Device, queue: &wgpu::Queue) -> Compaction {
+        let mut compaction = Compaction::default();
+        let len = self.len() as u32;
+
+        // a buffer can't be copied within itself, so the moved chunks go through a staging buffer
+        let mut moves = Vec::new();
+        while self.used_slots() > len {
+            let from = self.used_slots() - 1;
+            let Some(chunk_position) = self.owners.pop().unwrap() else {
+                self.free_slots.remove(&from);
+                continue;
+            };
+            let to = self.free_slots.pop_first().unwrap();
+            self.owners[to as usize] = Some(chunk_position);
+            self.slots.insert(chunk_position, to);
+            moves.push((from, to));
+            compaction.moved.push(chunk_position);
+        }
+        if !moves.is_empty() {
+            let staging_buffer = device.create_buffer(&wgpu::BufferDescriptor {
+                label: Some("Voxels Compaction Staging Buffer"),
+                size: moves.len() as u64 * CHUNK_SLOT_SIZE,
+                usage: wgpu::BufferUsages::COPY_SRC | wgpu::BufferUsages::COPY_DST,
+                mapped_at_creation: false,
+            });
+            let mut encoder = device.create_command_encoder(&wgpu::CommandEncoderDescriptor {
+                label: Some("Voxels Compaction Encoder"),
+            });
+            for (i, &(from, _)) in moves.iter().enumerate() {
+                encoder.copy_buffer_to_buffer(
+                    &self.storage_buffer,
+                    from as u64 * CHUNK_SLOT_SIZE,
+                    &staging_buffer,
+                    i as u64 * CHUNK_SLOT_SIZE,
+                    CHUNK_SLOT_SIZE,
+                );
+            }
+            for (i, &(_, to)) in moves.iter().enumerate() {
+                encoder.copy_buffer_to_buffer(
+                    &staging_buffer,
+                    i as u64 * CHUNK_SLOT_SIZE,
+                    &self.storage_buffer,
+                    to as u64 * CHUNK_SLOT_SIZE,
+                    CHUNK_SLOT_SIZE,
+                );
+            }
+            queue.submit([encoder.finish()]);
+        }
+
+        let mut capacity = self.capacity();
+        while capacity > 1 && capacity > len * 4 {
+            capacity /= 2;
+        }
+        if capacity != self.capacity() {
+            self.storage_buffer
+                .set_size(device, queue, capacity as u64 * CHUNK_SLOT_SIZE);
+            compaction.reallocated = true;
+        }
+        compaction
+    }
+}
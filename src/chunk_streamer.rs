@@ -0,0 +1,200 @@
+use std::collections::HashSet;
+use std::sync::{mpsc, Arc, Mutex};
+
+use crate::{Chunk, TerrainGenerator, World, CHUNK_SIZE};
+
+/// Something that can produce the contents of any chunk, from any thread
+pub trait ChunkSource: Send + Sync + 'static {
+    fn load_chunk(&self, chunk_position: cgmath::Vector4<i32>) -> Chunk;
+}
+
+impl ChunkSource for TerrainGenerator {
+    fn load_chunk(&self, chunk_position: cgmath::Vector4<i32>) -> Chunk {
+        self.generate_chunk(chunk_position)
+    }
+}
+
+/// The chunks that the streamer currently wants, shared with the worker thread
+/// so it can skip requests that went out of range while they were queued
+#[derive(Clone, Copy, Debug, PartialEq)]
+struct StreamingArea {
+    center: cgmath::Vector4<i32>,
+    radius: f32,
+}
+
+impl StreamingArea {
+    fn contains(&self, chunk_position: cgmath::Vector4<i32>, margin: f32) -> bool {
+        let offset = (chunk_position - self.center).cast::<f32>().unwrap();
+        let radius = self.radius + margin;
+        offset.x * offset.x + offset.y * offset.y + offset.z * offset.z + offset.w * offset.w
+            <= radius * radius
+    }
+}
+
+/// Keeps the chunks within a 4D radius of the camera loaded into a `World`,
+/// loading them from a `ChunkSource` on a background thread
+///
+/// Chunks are unloaded once they are a chunk further away than the radius, so moving back and forth
+/// across a chunk boundary doesn't keep reloading them. Any edits to unloaded chunks are lost.
+pub struct ChunkStreamer {
+    /// The distance in chunks from the camera's chunk within which chunks are loaded
+    pub radius: f32,
+    area: Arc<Mutex<Option<StreamingArea>>>,
+    /// `None` once the streamer is being dropped, or the thread has stopped because the `ChunkSource` panicked
+    requests: Option<mpsc::Sender<cgmath::Vector4<i32>>>,
+    results: mpsc::Receiver<(cgmath::Vector4<i32>, Option<Chunk>)>,
+    thread: Option<std::thread::JoinHandle<()>>,
+    /// Chunks that have been requested but haven't come back yet
+    pending: HashSet<cgmath::Vector4<i32>>,
+    /// Chunks that have been loaded into the world, including ones that were empty and so aren't in it
+    loaded: HashSet<cgmath::Vector4<i32>>,
+    last_area: Option<StreamingArea>,
+}
+
+impl ChunkStreamer {
+    pub fn new(source: impl ChunkSource, radius: f32) -> Self {
+        let area = Arc::new(Mutex::new(None::<StreamingArea>));
+        let (request_sender, request_receiver) = mpsc::channel::<cgmath::Vector4<i32>>();
+        let (result_sender, result_receiver) = mpsc::channel();
+
+        let thread = std::thread::Builder::new()
+            .name("Chunk Streamer".into())
+            .spawn({
+                let area = area.clone();
+                move || {
+                    // stops once the streamer is dropped and the sender along with it
+                    for chunk_position in request_receiver {
+                        let wanted = area
+                            .lock()
+                            .unwrap()
+                            .is_some_and(|area| area.contains(chunk_position, 0.0));
+                        let chunk = wanted.then(|| source.load_chunk(chunk_position));
+                        if result_sender.send((chunk_position, chunk)).is_err() {
+                            break;
+                        }
+                    }
+                }
+            })
+            .unwrap();
+
+        Self {
+            radius,
+            area,
+            requests: Some(request_sender),
+            results: result_receiver,
+            thread: Some(thread),
+            pending: HashSet::new(),
+            loaded: HashSet::new(),
+            last_area: None,
+        }
+    }
+
+    /// The number of chunks that are waiting to be loaded
+    pub fn pending_count(&self) -> usize {
+        self.pending.len()
+    }
+
+    /// Requests the chunks that have come into range of `camera_position`,
+    /// unloads the ones that have gone out of range and inserts the ones that are ready,
+    /// never waiting for the background thread
+    pub fn update(&mut self, world: &mut World, camera_position: cgmath::Vector4<f32>) {
+        let area = StreamingArea {
+            center: camera_position.map(|x| (x / CHUNK_SIZE as f32).floor() as i32),
+            radius: self.radius.max(0.0),
+        };
+
+        if self.last_area != Some(area) {
+            self.last_area = Some(area);
+            *self.area.lock().unwrap() = Some(area);
+
+            // Unload
+            self.loaded.retain(|&chunk_position| {
+                let keep = area.contains(chunk_position, 1.0);
+                if !keep {
+                    world.remove_chunk(chunk_position);
+                }
+                keep
+            });
+
+            // Request, closest first
+            let extent = area.radius.floor() as i32;
+            let mut wanted = Vec::new();
+            for w in -extent..=extent {
+                for z in -extent..=extent {
+                    for y in -extent..=extent {
+                        for x in -extent..=extent {
+                            let chunk_position = area.center + cgmath::vec4(x, y, z, w);
+                            if area.contains(chunk_position, 0.0)
+                                && !self.loaded.contains(&chunk_position)
+                                && !self.pending.contains(&chunk_position)
+                            {
+                                wanted.push((x * x + y * y + z * z + w * w, chunk_position));
+                            }
+                        }
+                    }
+                }
+            }
+            wanted.sort_by_key(|&(distance_squared, _)| distance_squared);
+            for (_, chunk_position) in wanted {
+                self.request(chunk_position);
+            }
+        }
+
+        // Insert the chunks that are ready
+        while let Ok((chunk_position, chunk)) = self.results.try_recv() {
+            self.pending.remove(&chunk_position);
+            let Some(chunk) = chunk else {
+                // skipped because it was out of range, but the camera may have come back since
+                if area.contains(chunk_position, 0.0) {
+                    self.request(chunk_position);
+                }
+                continue;
+            };
+            if !area.contains(chunk_position, 1.0) {
+                continue;
+            }
+            let mut chunk = chunk;
+            // edits made while it was loading have created the chunk already, so the loaded voxels only
+            // fill in where nothing was placed. Voxels broken in the meantime can't be told apart from
+            // ones that were never touched, and so come back.
+            if let Some(edited) = world.chunk(chunk_position) {
+                for (voxel, &edited) in chunk.data.iter_mut().zip(&edited.data) {
+                    if !edited.is_empty() {
+                        *voxel = edited;
+                    }
+                }
+            }
+            if !chunk.is_empty() {
+                world.insert_chunk(chunk_position, chunk);
+            }
+            self.loaded.insert(chunk_position);
+        }
+    }
+
+    /// Queues `chunk_position` for the thread, unless it has stopped
+    fn request(&mut self, chunk_position: cgmath::Vector4<i32>) {
+        let Some(requests) = &self.requests else {
+            return;
+        };
+        if requests.send(chunk_position).is_ok() {
+            self.pending.insert(chunk_position);
+            return;
+        }
+        // the thread only stops early if the `ChunkSource` panicked, so nothing more is coming
+        eprintln!("the chunk streamer's thread has stopped, so no more chunks will be loaded");
+        self.requests = None;
+        self.pending.clear();
+    }
+}
+
+impl Drop for ChunkStreamer {
+    fn drop(&mut self) {
+        // makes the thread skip whatever is still queued
+        *self.area.lock().unwrap() = None;
+        self.requests = None;
+        if let Some(thread) = self.thread.take() {
+            // the thread only fails if the `ChunkSource` panicked, which has been reported already
+            let _ = thread.join();
+        }
+    }
+}
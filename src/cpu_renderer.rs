@@ -0,0 +1,255 @@
+use cgmath::{ElementWise, InnerSpace};
+
+use crate::{
+    get_intersection, get_intersection_through, Camera, Environment, Hit, Lighting, Ray, Voxel,
+    World, MAX_BOUNCES, MAX_LIGHT_LEVEL, SECONDARY_RAY_OFFSET,
+};
+
+/// What the weights of all 26 neighbours in `get_ambient_occlusion` add up to, wherever the hit is on the face
+const AMBIENT_OCCLUSION_TOTAL_WEIGHT: f32 = 7.0;
+
+/// A port of `is_in_shadow` in `ray_tracing.wgsl`
+///
+/// Faces that point away from the sun are always in shadow, and nothing else is if shadows are off
+pub fn is_in_shadow(world: &World, lighting: &Lighting, hit: &Hit, max_distance: f32) -> bool {
+    if lighting.diffuse(hit.normal) <= 0.0 {
+        return true;
+    }
+    if !lighting.shadows {
+        return false;
+    }
+    // start in the voxel in front of the face, so the ray can't hit the voxel it leaves
+    let ray = Ray {
+        origin: hit.position + hit.normal * SECONDARY_RAY_OFFSET,
+        direction: lighting.normalized_sun_direction(),
+    };
+    get_intersection(world, ray, max_distance).is_some()
+}
+
+/// A port of `get_block_light` in `ray_tracing.wgsl`, the light level of the voxel in front of the face from 0 to 1
+pub fn get_block_light(world: &World, hit: &Hit) -> f32 {
+    let front = hit.voxel_position + hit.normal.cast::<i32>().unwrap();
+    world.get_voxel(front).light as f32 / MAX_LIGHT_LEVEL as f32
+}
+
+/// A port of `get_ambient_occlusion` in `ray_tracing.wgsl`
+///
+/// Weighs the occupied neighbours of the voxel in front of the face, within the 3D hyperface,
+/// by how close the hit is to them, from 0 for none to 1 for a face in a corner closed off along every axis
+pub fn get_ambient_occlusion(world: &World, hit: &Hit) -> f32 {
+    let normal = hit.normal.cast::<i32>().unwrap();
+    let front = hit.voxel_position + normal;
+    let local_position = hit.position - hit.voxel_position.cast::<f32>().unwrap();
+    let tangents = (0..4).filter(|&axis| normal[axis] == 0).collect::<Vec<_>>();
+
+    let mut occlusion = 0.0;
+    for i in 0..27 {
+        let offsets = [i % 3 - 1, i / 3 % 3 - 1, i / 9 - 1];
+        let mut neighbour = front;
+        let mut weight = 1.0;
+        for (&axis, &offset) in tangents.iter().zip(&offsets) {
+            neighbour[axis] += offset;
+            let f = local_position[axis];
+            weight *= match offset {
+                1 => f,
+                -1 => 1.0 - f,
+                _ => 1.0,
+            };
+        }
+        if offsets != [0, 0, 0] && !world.get_voxel(neighbour).is_empty() {
+            occlusion += weight;
+        }
+    }
+    occlusion / AMBIENT_OCCLUSION_TOTAL_WEIGHT
+}
+
+/// A port of `get_hit_color` in `ray_tracing.wgsl`, without the highlight
+pub fn get_hit_color(
+    world: &World,
+    lighting: &Lighting,
+    hit: &Hit,
+    max_distance: f32,
+) -> cgmath::Vector3<f32> {
+    let material_color = world.materials()[hit.voxel.material as usize].color;
+    let color = lighting.shade(
+        material_color,
+        hit.normal,
+        is_in_shadow(world, lighting, hit, max_distance),
+    ) + material_color * lighting.block_light * get_block_light(world, hit);
+    if lighting.ambient_occlusion {
+        color * (1.0 - lighting.ambient_occlusion_strength * get_ambient_occlusion(world, hit))
+    } else {
+        color
+    }
+}
+
+/// A port of `PendingRay` in `ray_tracing.wgsl`
+struct PendingRay {
+    ray: Ray,
+    /// How much of the light coming back along the ray reaches the camera
+    throughput: cgmath::Vector3<f32>,
+    /// What the ray is travelling through, so that it stops where that changes
+    medium: Voxel,
+    bounces: u32,
+}
+
+/// The same as `reflect` in WGSL
+fn reflect(direction: cgmath::Vector4<f32>, normal: cgmath::Vector4<f32>) -> cgmath::Vector4<f32> {
+    direction - normal * (2.0 * normal.dot(direction))
+}
+
+/// The same as `refract` in WGSL, but `None` rather than zero when all of the light is reflected
+fn refract(
+    direction: cgmath::Vector4<f32>,
+    normal: cgmath::Vector4<f32>,
+    eta: f32,
+) -> Option<cgmath::Vector4<f32>> {
+    let cos = normal.dot(direction);
+    let k = 1.0 - eta * eta * (1.0 - cos * cos);
+    (k >= 0.0).then(|| direction * eta - normal * (eta * cos + k.sqrt()))
+}
+
+fn refractive_index(world: &World, voxel: Voxel) -> f32 {
+    if voxel.is_empty() {
+        1.0
+    } else {
+        world.materials()[voxel.material as usize].refractive_index
+    }
+}
+
+/// A port of `ray_trace` in `ray_tracing.wgsl`
+///
+/// Follows the rays that are reflected off and refracted through the voxels that are hit,
+/// until they've bounced `Lighting::max_bounces` times
+pub fn ray_trace(
+    world: &World,
+    camera: &Camera,
+    lighting: &Lighting,
+    environment: &Environment,
+    ray: Ray,
+) -> cgmath::Vector3<f32> {
+    let max_bounces = lighting.max_bounces.min(MAX_BOUNCES);
+    let mut color = cgmath::vec3(0.0, 0.0, 0.0);
+    let mut pending = vec![PendingRay {
+        ray,
+        throughput: cgmath::vec3(1.0, 1.0, 1.0),
+        medium: Voxel::EMPTY,
+        bounces: 0,
+    }];
+    while let Some(PendingRay {
+        ray,
+        throughput,
+        medium,
+        bounces,
+    }) = pending.pop()
+    {
+        let Some(hit) = get_intersection_through(world, ray, camera.max_distance, medium) else {
+            color += throughput.mul_element_wise(environment.background(lighting, ray.direction));
+            continue;
+        };
+
+        // the part of the light that the fog takes away is made up for by the sky
+        let fog = environment.fog(hit.distance);
+        color += throughput.mul_element_wise(environment.sky_color(ray.direction)) * fog;
+        let throughput = throughput * (1.0 - fog);
+        let reflected = |throughput| PendingRay {
+            ray: Ray {
+                origin: hit.position + hit.normal * SECONDARY_RAY_OFFSET,
+                direction: reflect(ray.direction, hit.normal),
+            },
+            throughput,
+            medium,
+            bounces: bounces + 1,
+        };
+        // into the voxel that was hit, unless all of the light is reflected
+        let transmitted = |throughput| {
+            let eta = refractive_index(world, medium) / refractive_index(world, hit.voxel);
+            match refract(ray.direction, hit.normal, eta) {
+                Some(direction) => PendingRay {
+                    ray: Ray {
+                        origin: hit.position - hit.normal * SECONDARY_RAY_OFFSET,
+                        direction,
+                    },
+                    throughput,
+                    medium: hit.voxel,
+                    bounces: bounces + 1,
+                },
+                None => reflected(throughput),
+            }
+        };
+        let can_bounce = bounces < max_bounces;
+
+        // leaving a transparent voxel, which bends the ray without lighting it
+        if hit.voxel.is_empty() {
+            if can_bounce {
+                pending.push(transmitted(throughput));
+            }
+            continue;
+        }
+
+        let material = world.materials()[hit.voxel.material as usize];
+        let surface = get_hit_color(world, lighting, &hit, camera.max_distance)
+            * material.opacity()
+            + material.color * material.emission;
+        color += throughput.mul_element_wise(surface);
+        if !can_bounce {
+            continue;
+        }
+        if material.reflectivity > 0.0 {
+            pending.push(reflected(throughput * material.reflectivity));
+        }
+        if material.transparency > 0.0 {
+            pending.push(transmitted(
+                throughput.mul_element_wise(material.color) * material.transparency,
+            ));
+        }
+    }
+    color
+}
+
+/// Renders the world the same way as `main` in `ray_tracing.wgsl` does, but on the CPU
+///
+/// Returns the image as tightly packed RGBA8 rows, starting at the top
+pub fn render_cpu(
+    world: &World,
+    camera: &Camera,
+    lighting: &Lighting,
+    environment: &Environment,
+    width: u32,
+    height: u32,
+) -> Vec<u8> {
+    let mut image = Vec::with_capacity(width as usize * height as usize * 4);
+    let aspect = width as f32 / height as f32;
+    for y in 0..height {
+        for x in 0..width {
+            let uv = cgmath::vec2(x as f32 / width as f32, y as f32 / height as f32);
+            let normalized_uv = cgmath::vec2(uv.x * 2.0 - 1.0, (1.0 - uv.y) * 2.0 - 1.0);
+
+            let ray = camera.ray(normalized_uv.extend(0.0), aspect);
+
+            let color = ray_trace(world, camera, lighting, environment, ray);
+            image.extend(
+                [color.x, color.y, color.z, 1.0]
+                    .map(|channel| (channel.clamp(0.0, 1.0) * 255.0).round() as u8),
+            );
+        }
+    }
+    image
+}
+
+/// Writes an image returned by `render_cpu` to a PNG file
+pub fn save_png(
+    path: impl AsRef<std::path::Path>,
+    width: u32,
+    height: u32,
+    rgba: &[u8],
+) -> std::io::Result<()> {
+    let file = std::io::BufWriter::new(std::fs::File::create(path)?);
+    let mut encoder = png::Encoder::new(file, width, height);
+    encoder.set_color(png::ColorType::Rgba);
+    encoder.set_depth(png::BitDepth::Eight);
+    let mut writer = encoder.write_header()?;
+    writer.write_image_data(rgba)?;
+    writer.finish()?;
+    Ok(())
+}
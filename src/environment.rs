@@ -0,0 +1,108 @@
+use cgmath::{Angle, InnerSpace, VectorSpace};
+use encase::ShaderType;
+
+use crate::Lighting;
+
+#[derive(ShaderType)]
+pub struct GpuEnvironment {
+    zenith_color: cgmath::Vector3<f32>,
+    horizon_color: cgmath::Vector3<f32>,
+    ground_color: cgmath::Vector3<f32>,
+    sun_disc_color: cgmath::Vector3<f32>,
+    sun_disc_cos: f32,
+    fog: u32,
+    fog_density: f32,
+}
+
+/// What rays that don't hit anything see, and the fog between the camera and what they do hit
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Environment {
+    /// The color of the sky straight up along Y
+    pub zenith_color: cgmath::Vector3<f32>,
+    /// The color of the sky along the 3D horizon, where Y is 0 and the other axes can be anything
+    pub horizon_color: cgmath::Vector3<f32>,
+    /// The color below the horizon, straight down along -Y
+    pub ground_color: cgmath::Vector3<f32>,
+    /// How far from `Lighting::sun_direction` the sun is drawn, or 0 for no sun disc
+    pub sun_disc_radius: cgmath::Deg<f32>,
+    pub sun_disc_color: cgmath::Vector3<f32>,
+    /// Whether hits fade towards the sky color behind them with distance
+    pub fog: bool,
+    /// How much of the light fog takes away per unit of distance, with the rest making it through exponentially
+    pub fog_density: f32,
+}
+
+impl Default for Environment {
+    fn default() -> Self {
+        Self {
+            zenith_color: cgmath::vec3(0.25, 0.45, 0.85),
+            horizon_color: cgmath::vec3(0.7, 0.8, 0.95),
+            ground_color: cgmath::vec3(0.3, 0.27, 0.25),
+            sun_disc_radius: cgmath::Deg(2.0),
+            sun_disc_color: cgmath::vec3(1.0, 0.95, 0.8),
+            fog: false,
+            fog_density: 0.05,
+        }
+    }
+}
+
+impl Environment {
+    /// A port of `get_sky_color` in `ray_tracing.wgsl`, the light coming from the sky along `direction`,
+    /// without the sun disc
+    pub fn sky_color(&self, direction: cgmath::Vector4<f32>) -> cgmath::Vector3<f32> {
+        let up = direction.y / direction.magnitude();
+        if up >= 0.0 {
+            self.horizon_color.lerp(self.zenith_color, up.sqrt())
+        } else {
+            self.horizon_color.lerp(self.ground_color, (-up).sqrt())
+        }
+    }
+
+    /// A port of `get_environment_color` in `ray_tracing.wgsl`, what a ray going along `direction` sees
+    /// when it doesn't hit anything
+    pub fn background(
+        &self,
+        lighting: &Lighting,
+        direction: cgmath::Vector4<f32>,
+    ) -> cgmath::Vector3<f32> {
+        if direction
+            .normalize()
+            .dot(lighting.normalized_sun_direction())
+            >= self.sun_disc_cos()
+        {
+            self.sun_disc_color
+        } else {
+            self.sky_color(direction)
+        }
+    }
+
+    /// A port of `get_fog` in `ray_tracing.wgsl`, how much of a hit `distance` away is replaced by the sky
+    pub fn fog(&self, distance: f32) -> f32 {
+        if self.fog {
+            1.0 - (-self.fog_density * distance).exp()
+        } else {
+            0.0
+        }
+    }
+
+    /// Directions closer to the sun than this are in the disc, which is more than any can be when there's no disc
+    fn sun_disc_cos(&self) -> f32 {
+        if self.sun_disc_radius.0 > 0.0 {
+            self.sun_disc_radius.cos()
+        } else {
+            2.0
+        }
+    }
+
+    pub fn gpu(&self) -> GpuEnvironment {
+        GpuEnvironment {
+            zenith_color: self.zenith_color,
+            horizon_color: self.horizon_color,
+            ground_color: self.ground_color,
+            sun_disc_color: self.sun_disc_color,
+            sun_disc_cos: self.sun_disc_cos(),
+            fog: self.fog as u32,
+            fog_density: self.fog_density,
+        }
+    }
+}
@@ -0,0 +1,217 @@
+use eframe::wgpu;
+use encase::{ShaderSize, ShaderType};
+
+use crate::StorageBuffer;
+
+/// The value of entries that have never been used, which end the search for a key
+pub const UNUSED_ENTRY: u32 = u32::MAX;
+/// The value of entries whose key was removed, which the search for other keys carries on past
+pub const REMOVED_ENTRY: u32 = u32::MAX - 1;
+/// The fewest entries a table has, which is also how many it starts out with
+const MIN_CAPACITY: usize = 16;
+/// The size of `GpuHashTableData::mask`, padded to the alignment of the entries
+const HASH_TABLE_HEADER_SIZE: wgpu::BufferAddress = 16;
+const HASH_ENTRY_SIZE: wgpu::BufferAddress = <GpuHashEntry as ShaderSize>::SHADER_SIZE.get();
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, ShaderType)]
+pub struct GpuHashEntry {
+    pub key: cgmath::Vector4<i32>,
+    /// Tells apart the same `key` at different levels of a tree, such as cells of `ChunkOccupancy`
+    pub level: u32,
+    /// `UNUSED_ENTRY`, `REMOVED_ENTRY`, or what the key maps to
+    pub value: u32,
+}
+
+impl GpuHashEntry {
+    const UNUSED: Self = Self {
+        key: cgmath::vec4(0, 0, 0, 0),
+        level: 0,
+        value: UNUSED_ENTRY,
+    };
+}
+
+/// A hash table with open addressing and linear probing, searched by `get_chunk_slot` and `is_cell_occupied` in `ray_tracing.wgsl`
+#[derive(ShaderType)]
+pub struct GpuHashTableData<'a> {
+    /// One less than the number of entries, which is a power of two
+    pub mask: u32,
+    #[size(runtime)]
+    pub entries: &'a [GpuHashEntry],
+}
+
+/// A port of `hash_key` in `ray_tracing.wgsl`, where in a `GpuHashTable` the search for a key starts
+pub fn hash_key(key: cgmath::Vector4<i32>, level: u32) -> u32 {
+    let mut hash = (key.x as u32).wrapping_mul(0x8da6b343)
+        ^ (key.y as u32).wrapping_mul(0xd8163841)
+        ^ (key.z as u32).wrapping_mul(0xcb1ab31f)
+        ^ (key.w as u32).wrapping_mul(0x9e3779b1)
+        ^ level.wrapping_mul(0x2545f491);
+    // so that the low bits, which pick the entry, depend on the high bits too
+    hash ^= hash >> 16;
+    hash = hash.wrapping_mul(0x7feb352d);
+    hash ^= hash >> 15;
+    hash = hash.wrapping_mul(0x846ca68b);
+    hash ^= hash >> 16;
+    hash
+}
+
+/// A map from 4D keys to `u32`s that the GPU can look up, kept on the CPU as well to find what changed
+///
+/// The table is never more than half full, counting removed entries, so every search ends at an unused one.
+/// Its size only depends on how many keys are in it, however far apart they are.
+pub struct GpuHashTable {
+    storage_buffer: StorageBuffer<'static>,
+    entries: Vec<GpuHashEntry>,
+    /// How many entries aren't unused, including the removed ones
+    used: usize,
+    /// The entries that changed since the last upload, or `None` if the whole table has to be written
+    changed: Option<Vec<usize>>,
+}
+
+impl GpuHashTable {
+    pub fn new(device: &wgpu::Device, label: &'static str) -> Self {
+        let storage_buffer = StorageBuffer::new(
+            device,
+            wgpu::BufferDescriptor {
+                label: Some(label),
+                size: <GpuHashTableData<'_> as ShaderType>::min_size().get(),
+                // `COPY_SRC` so that it can be read back to check it
+                usage: wgpu::BufferUsages::COPY_SRC
+                    | wgpu::BufferUsages::COPY_DST
+                    | wgpu::BufferUsages::STORAGE,
+                mapped_at_creation: false,
+            },
+        );
+        Self {
+            storage_buffer,
+            entries: vec![GpuHashEntry::UNUSED; MIN_CAPACITY],
+            used: 0,
+            changed: None,
+        }
+    }
+
+    pub fn buffer(&self) -> &wgpu::Buffer {
+        &self.storage_buffer
+    }
+
+    /// How many entries the table has room for
+    pub fn capacity(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn get(&self, key: cgmath::Vector4<i32>, level: u32) -> Option<u32> {
+        self.find(key, level)
+            .ok()
+            .map(|index| self.entries[index].value)
+            .filter(|&value| value != REMOVED_ENTRY)
+    }
+
+    /// Maps `key` at `level` to `value`, or removes it if `value` is `None`
+    ///
+    /// `value` has to be less than `REMOVED_ENTRY`
+    pub fn set(&mut self, key: cgmath::Vector4<i32>, level: u32, value: Option<u32>) {
+        assert!(value.is_none_or(|value| value < REMOVED_ENTRY));
+        match (self.find(key, level), value) {
+            (Ok(index), value) => self.write(
+                index,
+                GpuHashEntry {
+                    key,
+                    level,
+                    value: value.unwrap_or(REMOVED_ENTRY),
+                },
+            ),
+            (Err(_), None) => {}
+            (Err(index), Some(value)) => {
+                if self.entries[index].value == UNUSED_ENTRY {
+                    if (self.used + 1) * 2 > self.entries.len() {
+                        self.rebuild();
+                        self.set(key, level, Some(value));
+                        return;
+                    }
+                    self.used += 1;
+                }
+                self.write(index, GpuHashEntry { key, level, value });
+            }
+        }
+    }
+
+    /// Removes every key, shrinking the table back down
+    pub fn clear(&mut self) {
+        self.entries = vec![GpuHashEntry::UNUSED; MIN_CAPACITY];
+        self.used = 0;
+        self.changed = None;
+    }
+
+    /// Writes the entries that changed since the last upload
+    ///
+    /// Returns whether the buffer was reallocated
+    pub fn upload(&mut self, device: &wgpu::Device, queue: &wgpu::Queue) -> bool {
+        let Some(changed) = self.changed.replace(Vec::new()) else {
+            let table = GpuHashTableData {
+                mask: self.entries.len() as u32 - 1,
+                entries: &self.entries,
+            };
+            let mut storage_buffer = encase::StorageBuffer::new(Vec::with_capacity(
+                (HASH_TABLE_HEADER_SIZE + self.entries.len() as u64 * HASH_ENTRY_SIZE) as usize,
+            ));
+            storage_buffer.write(&table).unwrap();
+            return self
+                .storage_buffer
+                .set_data_lossy(device, queue, &storage_buffer.into_inner());
+        };
+        for index in changed {
+            let mut entry =
+                encase::StorageBuffer::new(Vec::with_capacity(HASH_ENTRY_SIZE as usize));
+            entry.write(&self.entries[index]).unwrap();
+            self.storage_buffer.write_range(
+                queue,
+                HASH_TABLE_HEADER_SIZE + index as u64 * HASH_ENTRY_SIZE,
+                &entry.into_inner(),
+            );
+        }
+        false
+    }
+
+    /// The index of the entry for `key`, or if it isn't in the table, the index of the entry to put it in
+    fn find(&self, key: cgmath::Vector4<i32>, level: u32) -> Result<usize, usize> {
+        let mask = self.entries.len() - 1;
+        let mut index = hash_key(key, level) as usize & mask;
+        let mut removed = None;
+        loop {
+            let entry = &self.entries[index];
+            if entry.value == UNUSED_ENTRY {
+                return Err(removed.unwrap_or(index));
+            }
+            if entry.key == key && entry.level == level {
+                return Ok(index);
+            }
+            if entry.value == REMOVED_ENTRY && removed.is_none() {
+                removed = Some(index);
+            }
+            index = (index + 1) & mask;
+        }
+    }
+
+    fn write(&mut self, index: usize, entry: GpuHashEntry) {
+        self.entries[index] = entry;
+        if let Some(changed) = &mut self.changed {
+            changed.push(index);
+        }
+    }
+
+    /// Puts the keys that haven't been removed into a table with room for as many again three times over
+    fn rebuild(&mut self) {
+        let entries = std::mem::take(&mut self.entries);
+        let live = entries
+            .into_iter()
+            .filter(|entry| entry.value < REMOVED_ENTRY)
+            .collect::<Vec<_>>();
+        let capacity = ((live.len() + 1) * 4).next_power_of_two().max(MIN_CAPACITY);
+        self.entries = vec![GpuHashEntry::UNUSED; capacity];
+        self.used = 0;
+        self.changed = None;
+        for entry in live {
+            self.set(entry.key, entry.level, Some(entry.value));
+        }
+    }
+}
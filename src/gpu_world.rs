@@ -0,0 +1,326 @@
+use eframe::wgpu;
+use encase::{ArrayLength, ShaderType};
+use std::collections::HashSet;
+use std::ops::Range;
+
+use crate::{
+    Chunk, ChunkChanges, ChunkOccupancy, ChunkPool, GpuHashTable, GpuHashTableData, Materials,
+    StorageBuffer, World, CHUNK_VOLUME, OCCUPANCY_LEVELS,
+};
+
+/// The GPU side copy of a `World`, bound as the tesseracts bind group
+///
+/// Each chunk keeps the same slot in the voxel pool until it's removed or the pool is compacted,
+/// so only the voxels that have changed since the last update are uploaded
+pub struct GpuWorld {
+    materials_storage_buffer: StorageBuffer<'static>,
+    /// The slot in the voxel pool of each chunk, at level 0
+    chunk_table: GpuHashTable,
+    chunk_pool: ChunkPool,
+    /// The occupied cells of every level of `World::occupancy`, with the level they're at
+    occupancy: GpuHashTable,
+    pub bind_group_layout: wgpu::BindGroupLayout,
+    pub bind_group: wgpu::BindGroup,
+    /// Whether the chunk table and occupancy have been cleared since they were last uploaded,
+    /// so they have to be uploaded even if no chunks changed
+    chunk_table_dirty: bool,
+}
+
+impl GpuWorld {
+    pub fn new(device: &wgpu::Device) -> Self {
+        let materials_storage_buffer = StorageBuffer::new(
+            device,
+            wgpu::BufferDescriptor {
+                label: Some("Materials Storage Buffer"),
+                size: <Materials<'_> as ShaderType>::min_size().get(),
+                usage: wgpu::BufferUsages::COPY_DST | wgpu::BufferUsages::STORAGE,
+                mapped_at_creation: false,
+            },
+        );
+        let chunk_table = GpuHashTable::new(device, "Chunk Table Storage Buffer");
+        let chunk_pool = ChunkPool::new(device);
+        let occupancy = GpuHashTable::new(device, "Occupancy Storage Buffer");
+        let bind_group_layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            label: Some("Tesseracts Bind Group Layout"),
+            entries: &[
+                wgpu::BindGroupLayoutEntry {
+                    binding: 0,
+                    visibility: wgpu::ShaderStages::COMPUTE,
+                    ty: wgpu::BindingType::Buffer {
+                        ty: wgpu::BufferBindingType::Storage { read_only: true },
+                        has_dynamic_offset: false,
+                        min_binding_size: Some(<Materials<'_> as ShaderType>::min_size()),
+                    },
+                    count: None,
+                },
+                wgpu::BindGroupLayoutEntry {
+                    binding: 1,
+                    visibility: wgpu::ShaderStages::COMPUTE,
+                    ty: wgpu::BindingType::Buffer {
+                        ty: wgpu::BufferBindingType::Storage { read_only: true },
+                        has_dynamic_offset: false,
+                        min_binding_size: Some(<GpuHashTableData<'_> as ShaderType>::min_size()),
+                    },
+                    count: None,
+                },
+                wgpu::BindGroupLayoutEntry {
+                    binding: 2,
+                    visibility: wgpu::ShaderStages::COMPUTE,
+                    ty: wgpu::BindingType::Buffer {
+                        ty: wgpu::BufferBindingType::Storage { read_only: true },
+                        has_dynamic_offset: false,
+                        min_binding_size: Some(<Chunk as ShaderType>::min_size()),
+                    },
+                    count: None,
+                },
+                wgpu::BindGroupLayoutEntry {
+                    binding: 3,
+                    visibility: wgpu::ShaderStages::COMPUTE,
+                    ty: wgpu::BindingType::Buffer {
+                        ty: wgpu::BufferBindingType::Storage { read_only: true },
+                        has_dynamic_offset: false,
+                        min_binding_size: Some(<GpuHashTableData<'_> as ShaderType>::min_size()),
+                    },
+                    count: None,
+                },
+            ],
+        });
+        let bind_group = Self::create_bind_group(
+            device,
+            &bind_group_layout,
+            &materials_storage_buffer,
+            chunk_table.buffer(),
+            chunk_pool.buffer(),
+            occupancy.buffer(),
+        );
+
+        Self {
+            materials_storage_buffer,
+            chunk_table,
+            chunk_pool,
+            occupancy,
+            bind_group_layout,
+            bind_group,
+            chunk_table_dirty: true,
+        }
+    }
+
+    fn create_bind_group(
+        device: &wgpu::Device,
+        layout: &wgpu::BindGroupLayout,
+        materials_storage_buffer: &wgpu::Buffer,
+        chunk_table_storage_buffer: &wgpu::Buffer,
+        voxels_storage_buffer: &wgpu::Buffer,
+        occupancy_storage_buffer: &wgpu::Buffer,
+    ) -> wgpu::BindGroup {
+        device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: Some("Tesseracts Bind Group"),
+            layout,
+            entries: &[
+                wgpu::BindGroupEntry {
+                    binding: 0,
+                    resource: wgpu::BindingResource::Buffer(wgpu::BufferBinding {
+                        buffer: materials_storage_buffer,
+                        offset: 0,
+                        size: None,
+                    }),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 1,
+                    resource: wgpu::BindingResource::Buffer(wgpu::BufferBinding {
+                        buffer: chunk_table_storage_buffer,
+                        offset: 0,
+                        size: None,
+                    }),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 2,
+                    resource: wgpu::BindingResource::Buffer(wgpu::BufferBinding {
+                        buffer: voxels_storage_buffer,
+                        offset: 0,
+                        size: None,
+                    }),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 3,
+                    resource: wgpu::BindingResource::Buffer(wgpu::BufferBinding {
+                        buffer: occupancy_storage_buffer,
+                        offset: 0,
+                        size: None,
+                    }),
+                },
+            ],
+        })
+    }
+
+    /// Uploads the parts of `world` that have changed since the last call
+    ///
+    /// Returns whether anything had changed
+    pub fn update(
+        &mut self,
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        world: &mut World,
+    ) -> bool {
+        let mut bind_group_invalidated = false;
+
+        // Upload materials
+        let materials_changed = world.take_materials_dirty();
+        if materials_changed {
+            let materials = Materials {
+                count: ArrayLength,
+                data: world.materials(),
+            };
+            let mut materials_storage_buffer = encase::StorageBuffer::new(Vec::with_capacity(
+                std::mem::size_of_val(&materials.count) + std::mem::size_of_val(materials.data),
+            ));
+            materials_storage_buffer.write(&materials).unwrap();
+            let materials_buffer = materials_storage_buffer.into_inner();
+
+            bind_group_invalidated |=
+                self.materials_storage_buffer
+                    .set_data_lossy(device, queue, &materials_buffer);
+        }
+
+        // Upload chunks
+        let changed = match world.take_chunk_changes() {
+            ChunkChanges::All => {
+                self.chunk_pool.clear();
+                self.chunk_table.clear();
+                self.occupancy.clear();
+                self.chunk_table_dirty = true;
+                world
+                    .chunks()
+                    .map(|(position, _)| (position, 0..CHUNK_VOLUME))
+                    .collect::<Vec<_>>()
+            }
+            ChunkChanges::Chunks(changed) => changed.into_iter().collect(),
+        };
+        let chunks_changed = !changed.is_empty() || self.chunk_table_dirty;
+        if chunks_changed {
+            bind_group_invalidated |= self.upload_chunks(device, queue, world, &changed);
+        }
+
+        if bind_group_invalidated {
+            self.bind_group = Self::create_bind_group(
+                device,
+                &self.bind_group_layout,
+                &self.materials_storage_buffer,
+                self.chunk_table.buffer(),
+                self.chunk_pool.buffer(),
+                self.occupancy.buffer(),
+            );
+        }
+        materials_changed || chunks_changed
+    }
+
+    /// The pool holding the voxels of the chunks currently on the GPU
+    pub fn chunk_pool(&self) -> &ChunkPool {
+        &self.chunk_pool
+    }
+
+    /// The total size of the buffers holding the world on the GPU
+    pub fn buffer_size(&self) -> wgpu::BufferAddress {
+        self.materials_storage_buffer.size()
+            + self.chunk_table.buffer().size()
+            + self.chunk_pool.buffer().size()
+            + self.occupancy.buffer().size()
+    }
+
+    /// Uploads the changed ranges of the chunks at `changed` that still exist in `world`,
+    /// frees the slots of the ones that don't, and updates the chunk table to match
+    ///
+    /// Returns whether any buffers were reallocated
+    fn upload_chunks(
+        &mut self,
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        world: &World,
+        changed: &[(cgmath::Vector4<i32>, Range<usize>)],
+    ) -> bool {
+        let mut reallocated = false;
+        let mut table_changes = changed
+            .iter()
+            .map(|(position, _)| *position)
+            .collect::<Vec<_>>();
+
+        // Free first, so that compacting and the new chunks can make use of the slots
+        for (position, _) in changed {
+            if world.chunk(*position).is_none() {
+                self.chunk_pool.free(*position);
+            }
+        }
+        if self.chunk_pool.needs_compaction() {
+            let compaction = self.chunk_pool.compact(device, queue);
+            table_changes.extend(compaction.moved);
+            reallocated |= compaction.reallocated;
+        }
+
+        let mut written = Vec::new();
+        for (position, range) in changed {
+            if world.chunk(*position).is_none() {
+                continue;
+            }
+            if self.chunk_pool.slot(*position).is_some() {
+                written.push((*position, range.clone()));
+            } else {
+                self.chunk_pool.allocate(*position);
+                // whatever was in the slot before belonged to another chunk
+                written.push((*position, 0..CHUNK_VOLUME));
+            }
+        }
+        reallocated |= self.chunk_pool.reserve(device, queue);
+        for (position, range) in written {
+            self.chunk_pool
+                .write(queue, position, range, world.chunk(position).unwrap());
+        }
+
+        reallocated | self.update_chunk_table(device, queue, world, &table_changes)
+    }
+
+    /// Writes the slots of the chunks at `changed` into the chunk table and their cells into the occupancy
+    ///
+    /// Returns whether any buffers were reallocated
+    fn update_chunk_table(
+        &mut self,
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        world: &World,
+        changed: &[cgmath::Vector4<i32>],
+    ) -> bool {
+        for &position in changed {
+            self.chunk_table
+                .set(position, 0, self.chunk_pool.slot(position));
+        }
+        self.chunk_table_dirty = false;
+        let reallocated = self.chunk_table.upload(device, queue);
+
+        reallocated | self.update_occupancy(device, queue, world, changed)
+    }
+
+    /// Sets the cells containing the chunks at `changed` in the occupancy to whether they have any chunks in them
+    ///
+    /// Returns whether the buffer was reallocated
+    fn update_occupancy(
+        &mut self,
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        world: &World,
+        changed: &[cgmath::Vector4<i32>],
+    ) -> bool {
+        let mut visited = HashSet::new();
+        for &position in changed {
+            for level in 1..=OCCUPANCY_LEVELS {
+                let cell = ChunkOccupancy::cell(position, level);
+                if !visited.insert((level, cell)) {
+                    // so were all of the levels above
+                    break;
+                }
+                let occupied = world.occupancy().is_occupied(level, cell);
+                self.occupancy
+                    .set(cell, level as u32, occupied.then_some(1));
+            }
+        }
+        self.occupancy.upload(device, queue)
+    }
+}
@@ -1,11 +1,47 @@
 #![deny(elided_lifetimes_in_paths, single_use_lifetimes)]
+// the `ShaderType` derive emits compile time `check` functions that are never called
+#![allow(dead_code)]
 
 mod app;
+mod camera;
+mod chunk_pool;
+mod chunk_streamer;
+mod cpu_renderer;
+mod environment;
+mod gpu_hash_table;
+mod gpu_world;
+mod lighting;
+mod occupancy;
+mod projection;
+mod ray;
+mod renderer;
+mod scene;
 mod storage_buffer;
+mod terrain;
 mod texture;
+mod voxel_light;
+mod world;
+mod world_file;
 
 pub use app::*;
+pub use camera::*;
+pub use chunk_pool::*;
+pub use chunk_streamer::*;
+pub use cpu_renderer::*;
+pub use environment::*;
+pub use gpu_hash_table::*;
+pub use gpu_world::*;
+pub use lighting::*;
+pub use occupancy::*;
+pub use projection::*;
+pub use ray::*;
+pub use renderer::*;
+pub use scene::*;
 pub use storage_buffer::*;
+pub use terrain::*;
 pub use texture::*;
+pub use voxel_light::*;
+pub use world::*;
+pub use world_file::*;
 
 pub const CHUNK_SIZE: u32 = 4;
@@ -0,0 +1,97 @@
+use cgmath::InnerSpace;
+use encase::ShaderType;
+
+#[derive(ShaderType)]
+pub struct GpuLighting {
+    sun_direction: cgmath::Vector4<f32>,
+    sun_intensity: f32,
+    ambient: f32,
+    shadows: u32,
+    ambient_occlusion: u32,
+    ambient_occlusion_strength: f32,
+    max_bounces: u32,
+    block_light: f32,
+}
+
+/// How far shadow and bounced rays start from the face they leave, so that they start in the voxel on its side
+pub const SECONDARY_RAY_OFFSET: f32 = 1e-3;
+/// The most `Lighting::max_bounces` can be on the GPU, which keeps the rays still to be traced on a fixed size stack
+pub const MAX_BOUNCES: u32 = 8;
+
+/// A sun infinitely far away, plus light that reaches every face equally
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Lighting {
+    /// The direction towards the sun, which doesn't have to be normalized
+    pub sun_direction: cgmath::Vector4<f32>,
+    /// How much light a face pointing straight at the sun gets from it
+    pub sun_intensity: f32,
+    /// How much light every face gets, whichever way it points,
+    /// apart from when path traced where the light comes from the `Environment` instead
+    pub ambient: f32,
+    /// Whether voxels between a face and the sun block the sunlight
+    pub shadows: bool,
+    /// Whether faces get darker where voxels are next to the voxel in front of them
+    pub ambient_occlusion: bool,
+    /// How much of the light a face loses when it's fully occluded, from 0 to 1
+    pub ambient_occlusion_strength: f32,
+    /// How many times a ray can be reflected or refracted, up to `MAX_BOUNCES`
+    pub max_bounces: u32,
+    /// How much light a face gets from emissive voxels when the voxel in front of it is at `MAX_LIGHT_LEVEL`
+    pub block_light: f32,
+}
+
+impl Default for Lighting {
+    fn default() -> Self {
+        Self {
+            sun_direction: cgmath::vec4(0.4, 0.8, -0.5, 0.2),
+            sun_intensity: 0.65,
+            ambient: 0.35,
+            shadows: true,
+            ambient_occlusion: true,
+            ambient_occlusion_strength: 0.6,
+            max_bounces: 4,
+            block_light: 1.0,
+        }
+    }
+}
+
+impl Lighting {
+    /// `sun_direction` normalized, or zero if there is no direction to normalize
+    pub fn normalized_sun_direction(&self) -> cgmath::Vector4<f32> {
+        let length = self.sun_direction.magnitude();
+        if length > 0.0 {
+            self.sun_direction / length
+        } else {
+            cgmath::vec4(0.0, 0.0, 0.0, 0.0)
+        }
+    }
+
+    /// How much of the sunlight a face pointing along `normal` gets, before shadows
+    pub fn diffuse(&self, normal: cgmath::Vector4<f32>) -> f32 {
+        normal.dot(self.normalized_sun_direction()).max(0.0)
+    }
+
+    /// A port of `shade` in `ray_tracing.wgsl`, lighting `color` on a face pointing along `normal`
+    pub fn shade(
+        &self,
+        color: cgmath::Vector3<f32>,
+        normal: cgmath::Vector4<f32>,
+        in_shadow: bool,
+    ) -> cgmath::Vector3<f32> {
+        let diffuse = if in_shadow { 0.0 } else { self.diffuse(normal) };
+        color * (self.ambient + self.sun_intensity * diffuse)
+    }
+
+    pub fn gpu(&self) -> GpuLighting {
+        GpuLighting {
+            sun_direction: self.normalized_sun_direction(),
+            sun_intensity: self.sun_intensity,
+            ambient: self.ambient,
+            shadows: self.shadows as u32,
+            ambient_occlusion: self.ambient_occlusion as u32,
+            ambient_occlusion_strength: self.ambient_occlusion_strength,
+            max_bounces: self.max_bounces.min(MAX_BOUNCES),
+            block_light: self.block_light,
+        }
+    }
+}
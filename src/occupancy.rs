@@ -0,0 +1,48 @@
+use std::collections::HashMap;
+
+/// How many levels `ChunkOccupancy` has above the chunks themselves
+pub const OCCUPANCY_LEVELS: usize = 8;
+
+/// A sparse tree over chunk positions with 16 children per node, used to skip empty space when ray tracing
+///
+/// Level `k` splits space into cells of `2^k` chunks along each axis and counts the chunks within each one,
+/// so a cell that isn't stored has no chunks in it. Level 0 would be the chunks themselves, so it isn't stored.
+#[derive(Clone, Debug, Default)]
+pub struct ChunkOccupancy {
+    levels: [HashMap<cgmath::Vector4<i32>, u32>; OCCUPANCY_LEVELS],
+}
+
+impl ChunkOccupancy {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// The cell at `level` that contains the chunk at `chunk_position`
+    pub fn cell(chunk_position: cgmath::Vector4<i32>, level: usize) -> cgmath::Vector4<i32> {
+        chunk_position.map(|x| x >> level)
+    }
+
+    /// Whether there are any chunks within `cell` at `level`, which goes from 1 to `OCCUPANCY_LEVELS`
+    pub fn is_occupied(&self, level: usize, cell: cgmath::Vector4<i32>) -> bool {
+        self.levels[level - 1].contains_key(&cell)
+    }
+
+    /// Counts a chunk that wasn't there before
+    pub fn insert(&mut self, chunk_position: cgmath::Vector4<i32>) {
+        for (i, level) in self.levels.iter_mut().enumerate() {
+            *level.entry(Self::cell(chunk_position, i + 1)).or_default() += 1;
+        }
+    }
+
+    /// Stops counting a chunk that was inserted before
+    pub fn remove(&mut self, chunk_position: cgmath::Vector4<i32>) {
+        for (i, level) in self.levels.iter_mut().enumerate() {
+            let cell = Self::cell(chunk_position, i + 1);
+            let count = level.get_mut(&cell).expect("chunk was never inserted");
+            *count -= 1;
+            if *count == 0 {
+                level.remove(&cell);
+            }
+        }
+    }
+}
@@ -0,0 +1,64 @@
+use cgmath::Angle;
+use serde::{Deserialize, Serialize};
+
+/// Which screen axis `Projection::fov` and `Projection::orthographic_size` are measured along,
+/// the other axis follows from the aspect ratio
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum FovAxis {
+    Vertical,
+    Horizontal,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum ProjectionMode {
+    Perspective,
+    /// All rays are parallel to the forward axis, starting from a rectangle around the camera
+    Orthographic,
+}
+
+/// How rays fan out from the camera, both the GPU and `Camera::ray` go through this
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Projection {
+    pub mode: ProjectionMode,
+    /// The full angle between opposite edges of the screen, only used in perspective mode
+    pub fov: cgmath::Deg<f32>,
+    pub fov_axis: FovAxis,
+    /// The distance between opposite edges of the screen, only used in orthographic mode
+    pub orthographic_size: f32,
+}
+
+impl Projection {
+    pub const MIN_FOV: cgmath::Deg<f32> = cgmath::Deg(1.0);
+    pub const MAX_FOV: cgmath::Deg<f32> = cgmath::Deg(179.0);
+
+    /// Half of the screen along `fov_axis`,
+    /// at a distance of 1 in perspective mode or in world units in orthographic mode
+    pub fn half_extent(&self) -> f32 {
+        match self.mode {
+            ProjectionMode::Perspective => (self.fov / 2.0).tan(),
+            ProjectionMode::Orthographic => self.orthographic_size / 2.0,
+        }
+    }
+
+    /// Half of the screen along the right and up axes, the same as the start of `get_camera_ray` in `ray_tracing.wgsl`
+    ///
+    /// `aspect` is the width divided by the height
+    pub fn half_extents(&self, aspect: f32) -> cgmath::Vector2<f32> {
+        let half_extent = self.half_extent();
+        match self.fov_axis {
+            FovAxis::Vertical => cgmath::vec2(half_extent * aspect, half_extent),
+            FovAxis::Horizontal => cgmath::vec2(half_extent, half_extent / aspect),
+        }
+    }
+}
+
+impl Default for Projection {
+    fn default() -> Self {
+        Self {
+            mode: ProjectionMode::Perspective,
+            fov: cgmath::Deg(90.0),
+            fov_axis: FovAxis::Vertical,
+            orthographic_size: 4.0,
+        }
+    }
+}
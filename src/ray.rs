@@ -0,0 +1,185 @@
+use cgmath::InnerSpace;
+
+use crate::{ChunkOccupancy, Voxel, World, CHUNK_SIZE, OCCUPANCY_LEVELS};
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Ray {
+    pub origin: cgmath::Vector4<f32>,
+    pub direction: cgmath::Vector4<f32>,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Hit {
+    pub voxel_position: cgmath::Vector4<i32>,
+    pub voxel: Voxel,
+    pub distance: f32,
+    pub position: cgmath::Vector4<f32>,
+    pub normal: cgmath::Vector4<f32>,
+}
+
+/// A port of `get_intersection` in `ray_tracing.wgsl`, stepping through the world one voxel at a time,
+/// but jumping across the cells that `World::occupancy` says are empty
+///
+/// The voxel containing `ray.origin` is never reported, only the voxels the ray enters.
+/// `Hit::normal` points out of the face of the voxel the ray entered through,
+/// and `Hit::distance`, like `max_distance`, is measured in voxels whatever the length of `ray.direction`
+pub fn get_intersection(world: &World, ray: Ray, max_distance: f32) -> Option<Hit> {
+    traverse(world, ray, max_distance, Voxel::EMPTY, true)
+}
+
+/// The same as `get_intersection`, but for a ray travelling through voxels of the same material as `medium`,
+/// stopping at the first voxel that isn't, which can be an empty one
+pub fn get_intersection_through(
+    world: &World,
+    ray: Ray,
+    max_distance: f32,
+    medium: Voxel,
+) -> Option<Hit> {
+    traverse(world, ray, max_distance, medium, true)
+}
+
+/// The same as `get_intersection`, but visiting every voxel along the way rather than skipping empty space
+pub fn get_intersection_unaccelerated(world: &World, ray: Ray, max_distance: f32) -> Option<Hit> {
+    traverse(world, ray, max_distance, Voxel::EMPTY, false)
+}
+
+/// A port of `get_empty_cell` in `ray_tracing.wgsl`
+///
+/// Returns the lowest corner and size of the biggest cell around the voxel at `position` that has nothing in it,
+/// or `None` if its chunk is loaded
+fn get_empty_cell(
+    world: &World,
+    position: cgmath::Vector4<i32>,
+) -> Option<(cgmath::Vector4<i32>, i32)> {
+    let (chunk_position, _) = World::split_position(position);
+    if world.chunk(chunk_position).is_some() {
+        return None;
+    }
+    let mut level = 0;
+    while level < OCCUPANCY_LEVELS
+        && !world
+            .occupancy()
+            .is_occupied(level + 1, ChunkOccupancy::cell(chunk_position, level + 1))
+    {
+        level += 1;
+    }
+    let size = CHUNK_SIZE as i32;
+    Some((
+        ChunkOccupancy::cell(chunk_position, level).map(|x| (x << level) * size),
+        size << level,
+    ))
+}
+
+fn traverse(
+    world: &World,
+    ray: Ray,
+    max_distance: f32,
+    medium: Voxel,
+    skip_empty: bool,
+) -> Option<Hit> {
+    // how far along the ray you have to go to cross one unit along each axis,
+    // computed this way so that axes the ray doesn't move along are infinite rather than NaN
+    let length = ray.direction.magnitude();
+    let ray_step_size_per_unit_axis = ray.direction.map(|x| length / x.abs());
+    let mut map_check = ray.origin.map(|x| x.floor() as i32);
+    let mut step = cgmath::vec4(0, 0, 0, 0);
+    for i in 0..4 {
+        if ray.direction[i] < 0.0 {
+            step[i] = -1;
+        } else if ray.direction[i] > 0.0 {
+            step[i] = 1;
+        }
+    }
+    if step == cgmath::vec4(0, 0, 0, 0) {
+        return None;
+    }
+    // how far along the ray the next boundary of the voxel at `map_check` is along each axis
+    let next_boundaries = |map_check: cgmath::Vector4<i32>| {
+        let mut ray_lengths_per_axis = cgmath::vec4(0.0, 0.0, 0.0, 0.0);
+        for i in 0..4 {
+            if step[i] < 0 {
+                ray_lengths_per_axis[i] =
+                    (ray.origin[i] - map_check[i] as f32) * ray_step_size_per_unit_axis[i];
+            } else if step[i] > 0 {
+                ray_lengths_per_axis[i] =
+                    ((map_check[i] + 1) as f32 - ray.origin[i]) * ray_step_size_per_unit_axis[i];
+            }
+        }
+        ray_lengths_per_axis
+    };
+    let mut ray_lengths_per_axis = next_boundaries(map_check);
+
+    let mut distance = 0.0;
+    while distance < max_distance {
+        // axes the ray doesn't move along are never stepped
+        let mut smallest_length = 0;
+        for i in 0..4 {
+            if step[i] != 0
+                && (step[smallest_length] == 0
+                    || ray_lengths_per_axis[i] < ray_lengths_per_axis[smallest_length])
+            {
+                smallest_length = i;
+            }
+        }
+
+        map_check[smallest_length] += step[smallest_length];
+        distance = ray_lengths_per_axis[smallest_length];
+        ray_lengths_per_axis[smallest_length] += ray_step_size_per_unit_axis[smallest_length];
+
+        let voxel = world.get_voxel(map_check);
+        if voxel.material != medium.material {
+            let mut normal = cgmath::vec4(0.0, 0.0, 0.0, 0.0);
+            normal[smallest_length] = -step[smallest_length] as f32;
+            return Some(Hit {
+                voxel_position: map_check,
+                voxel,
+                distance,
+                position: ray.origin + ray.direction * (distance / length),
+                normal,
+            });
+        }
+
+        let Some((min, size)) = skip_empty
+            .then(|| get_empty_cell(world, map_check))
+            .flatten()
+        else {
+            continue;
+        };
+        // move to the last voxel within the cell, so that the next step leaves it
+        let max = min + cgmath::vec4(size, size, size, size);
+        let mut exit_axis = 0;
+        let mut exit_distances = cgmath::vec4(0.0, 0.0, 0.0, 0.0);
+        for i in 0..4 {
+            if step[i] < 0 {
+                exit_distances[i] =
+                    (ray.origin[i] - min[i] as f32) * ray_step_size_per_unit_axis[i];
+            } else if step[i] > 0 {
+                exit_distances[i] =
+                    (max[i] as f32 - ray.origin[i]) * ray_step_size_per_unit_axis[i];
+            }
+            if step[i] != 0
+                && (step[exit_axis] == 0 || exit_distances[i] < exit_distances[exit_axis])
+            {
+                exit_axis = i;
+            }
+        }
+        let exit_position = ray.origin + ray.direction * (exit_distances[exit_axis] / length);
+        for i in 0..4 {
+            // never back, which rounding could otherwise do and then repeat forever
+            let exit_voxel = (exit_position[i].floor() as i32).clamp(min[i], max[i] - 1);
+            if step[i] > 0 {
+                map_check[i] = map_check[i].max(exit_voxel);
+            } else if step[i] < 0 {
+                map_check[i] = map_check[i].min(exit_voxel);
+            }
+        }
+        map_check[exit_axis] = if step[exit_axis] > 0 {
+            max[exit_axis] - 1
+        } else {
+            min[exit_axis]
+        };
+        ray_lengths_per_axis = next_boundaries(map_check);
+    }
+
+    None
+}
@@ -1,4 +1,12 @@
 const CHUNK_SIZE: i32 = 4;
+const CHUNK_VOLUME: u32 = 256u;
+// the material of `Voxel::EMPTY`
+const EMPTY_MATERIAL: u32 = 0xffffffffu;
+// must match `OCCUPANCY_LEVELS` in `occupancy.rs`
+const OCCUPANCY_LEVELS: u32 = 8u;
+// must match `UNUSED_ENTRY` and `REMOVED_ENTRY` in `gpu_hash_table.rs`
+const UNUSED_ENTRY: u32 = 0xffffffffu;
+const REMOVED_ENTRY: u32 = 0xfffffffeu;
 
 @group(0)
 @binding(0)
@@ -9,16 +17,82 @@ struct Camera {
     forward: vec4<f32>,
     right: vec4<f32>,
     up: vec4<f32>,
-    fov: f32,
+    ana: vec4<f32>,
+    // half of the screen along `fov_axis`, at a distance of 1 or in world units when orthographic
+    half_extent: f32,
+    fov_axis: u32,
+    orthographic: u32,
     max_distance: f32,
 }
 
+const FOV_AXIS_VERTICAL: u32 = 0u;
+const FOV_AXIS_HORIZONTAL: u32 = 1u;
+
 @group(1)
 @binding(0)
 var<uniform> camera: Camera;
 
+struct Highlight {
+    position: vec4<i32>,
+    enabled: u32,
+}
+
+@group(1)
+@binding(1)
+var<uniform> highlight: Highlight;
+
+struct Lighting {
+    // normalized, or zero for no sun
+    sun_direction: vec4<f32>,
+    sun_intensity: f32,
+    ambient: f32,
+    shadows: u32,
+    ambient_occlusion: u32,
+    ambient_occlusion_strength: f32,
+    // at most `MAX_BOUNCES`
+    max_bounces: u32,
+    block_light: f32,
+}
+
+// must match `SECONDARY_RAY_OFFSET` in `lighting.rs`
+const SECONDARY_RAY_OFFSET: f32 = 1e-3;
+// must match `MAX_LIGHT_LEVEL` in `voxel_light.rs`
+const MAX_LIGHT_LEVEL: u32 = 15u;
+// must match `MAX_BOUNCES` in `lighting.rs`
+const MAX_BOUNCES: u32 = 8u;
+// every ray that is popped pushes at most 2 with one more bounce, so at most one is left over per bounce
+const RAY_STACK_SIZE: u32 = 9u;
+// what the weights of all 26 neighbours in `get_ambient_occlusion` add up to, wherever the hit is on the face
+const AMBIENT_OCCLUSION_TOTAL_WEIGHT: f32 = 7.0;
+
+@group(1)
+@binding(2)
+var<uniform> lighting: Lighting;
+
+struct Environment {
+    zenith_color: vec3<f32>,
+    horizon_color: vec3<f32>,
+    ground_color: vec3<f32>,
+    sun_disc_color: vec3<f32>,
+    // more than 1 when there's no sun disc
+    sun_disc_cos: f32,
+    fog: u32,
+    fog_density: f32,
+}
+
+@group(1)
+@binding(3)
+var<uniform> environment: Environment;
+
+const HIGHLIGHT_OUTLINE_WIDTH: f32 = 0.05;
+const HIGHLIGHT_OUTLINE_COLOR: vec3<f32> = vec3<f32>(1.0, 1.0, 1.0);
+
 struct Material {
     color: vec3<f32>,
+    reflectivity: f32,
+    transparency: f32,
+    refractive_index: f32,
+    emission: f32,
 }
 
 struct Materials {
@@ -32,11 +106,69 @@ var<storage> materials: Materials;
 
 struct Voxel {
     material: u32,
+    light: u32,
+}
+
+@group(2)
+@binding(2)
+var<storage> voxels: array<Voxel>;
+
+struct HashEntry {
+    key: vec4<i32>,
+    level: u32,
+    value: u32,
+}
+
+// open addressing with linear probing, where `mask` is one less than the number of entries
+struct HashTable {
+    mask: u32,
+    entries: array<HashEntry>,
 }
 
+// the slot in `voxels` of each chunk, at level 0
+@group(2)
+@binding(1)
+var<storage> chunk_table: HashTable;
+
+// level `i` splits space into cells of `2^i` chunks along each axis,
+// with an entry for each cell that has any chunks in it
 @group(2)
+@binding(3)
+var<storage> occupancy: HashTable;
+
+@group(3)
+@binding(0)
+var retina_output: texture_storage_2d_array<rgba8unorm, write>;
+
+@group(3)
 @binding(1)
-var<storage> chunk: array<Voxel>;
+var retina_input: texture_2d_array<f32>;
+
+@group(3)
+@binding(2)
+var accumulation_output: texture_storage_2d<rgba32float, write>;
+
+@group(3)
+@binding(3)
+var accumulation_input: texture_2d<f32>;
+
+struct Accumulation {
+    // how many samples `accumulation_input` adds up, where 0 means it hasn't been written yet
+    samples: u32,
+}
+
+@group(3)
+@binding(4)
+var<uniform> accumulation: Accumulation;
+
+const PI: f32 = 3.14159265;
+
+// must match `RETINA_LAYERS` in `renderer.rs`, `textureNumLayers` on a sampled texture doesn't translate to GLSL
+const RETINA_LAYERS: i32 = 15;
+
+// how opaque a layer of the retina is when compositing, from the middle layer out to the outermost ones
+const RETINA_CENTER_OPACITY: f32 = 0.8;
+const RETINA_EDGE_OPACITY: f32 = 0.1;
 
 struct Ray {
     origin: vec4<f32>,
@@ -46,46 +178,145 @@ struct Ray {
 struct Hit {
     hit: bool,
     block_index: u32,
+    // `EMPTY_MATERIAL` when leaving a transparent voxel
+    material: u32,
+    voxel_position: vec4<i32>,
     distance: f32,
     position: vec4<f32>,
     normal: vec4<f32>,
 }
 
 fn get_block_index(position: vec4<i32>) -> u32 {
-    // TODO: update this when there are multiple chunks
     return u32(position.x + position.y * CHUNK_SIZE + position.z * CHUNK_SIZE * CHUNK_SIZE + position.w * CHUNK_SIZE * CHUNK_SIZE * CHUNK_SIZE);
 }
 
-fn get_intersection(ray: Ray) -> Hit {
-    var hit: Hit;
-    hit.hit = false;
+fn get_chunk_position(position: vec4<i32>) -> vec4<i32> {
+    // integer division that rounds towards negative infinity
+    let bias = select(vec4<i32>(0), vec4<i32>(CHUNK_SIZE - 1), position < vec4<i32>(0));
+    return (position - bias) / CHUNK_SIZE;
+}
 
-    let ray_step_size_per_unit_axis = vec4<f32>(
-        length(ray.direction / ray.direction.x),
-        length(ray.direction / ray.direction.y),
-        length(ray.direction / ray.direction.z),
-        length(ray.direction / ray.direction.w),
-    );
-    var map_check = vec4<i32>(floor(ray.origin));
-    var step: vec4<i32>;
-    var ray_lengths_per_axis: vec4<f32>;
+// where the search for `key` starts in a `HashTable`, which must match `hash_key` in `gpu_hash_table.rs`
+fn hash_key(key: vec4<i32>, level: u32) -> u32 {
+    var hash = (bitcast<u32>(key.x) * 0x8da6b343u)
+        ^ (bitcast<u32>(key.y) * 0xd8163841u)
+        ^ (bitcast<u32>(key.z) * 0xcb1ab31fu)
+        ^ (bitcast<u32>(key.w) * 0x9e3779b1u)
+        ^ (level * 0x2545f491u);
+    hash ^= hash >> 16u;
+    hash *= 0x7feb352du;
+    hash ^= hash >> 15u;
+    hash *= 0x846ca68bu;
+    hash ^= hash >> 16u;
+    return hash;
+}
+
+// Returns the slot in `voxels` of the chunk at `chunk_position`, or `UNUSED_ENTRY` if there is no chunk loaded there
+fn get_chunk_slot(chunk_position: vec4<i32>) -> u32 {
+    var index = hash_key(chunk_position, 0u) & chunk_table.mask;
+    // the table is never more than half full, so this reaches an unused entry before going all the way around
+    for (var i = 0u; i <= chunk_table.mask; i += 1u) {
+        let entry = chunk_table.entries[index];
+        if entry.value == UNUSED_ENTRY {
+            break;
+        }
+        if all(entry.key == chunk_position) && entry.level == 0u {
+            return select(entry.value, UNUSED_ENTRY, entry.value == REMOVED_ENTRY);
+        }
+        index = (index + 1u) & chunk_table.mask;
+    }
+    return UNUSED_ENTRY;
+}
+
+// Returns the index into `voxels`, or `u32(-1)` if there is no chunk loaded at that position
+fn get_voxel_index(position: vec4<i32>) -> u32 {
+    let chunk_position = get_chunk_position(position);
+    let slot = get_chunk_slot(chunk_position);
+    if slot == UNUSED_ENTRY {
+        return u32(-1);
+    }
+
+    return slot * CHUNK_VOLUME + get_block_index(position - chunk_position * CHUNK_SIZE);
+}
+
+// `level` goes from 1 to `OCCUPANCY_LEVELS`
+fn is_cell_occupied(level: u32, cell: vec4<i32>) -> bool {
+    var index = hash_key(cell, level) & occupancy.mask;
+    // the table is never more than half full, so this reaches an unused entry before going all the way around
+    for (var i = 0u; i <= occupancy.mask; i += 1u) {
+        let entry = occupancy.entries[index];
+        if entry.value == UNUSED_ENTRY {
+            break;
+        }
+        if all(entry.key == cell) && entry.level == level {
+            return entry.value != REMOVED_ENTRY;
+        }
+        index = (index + 1u) & occupancy.mask;
+    }
+    return false;
+}
+
+struct EmptyCell {
+    min: vec4<i32>,
+    // 0 if there is no empty cell
+    size: i32,
+}
+
+// Returns the biggest cell around the voxel at `position` that has nothing in it,
+// where `voxel_index` is what `get_voxel_index` returned for it
+fn get_empty_cell(position: vec4<i32>, voxel_index: u32) -> EmptyCell {
+    var cell: EmptyCell;
+    cell.size = 0;
+    if voxel_index != u32(-1) {
+        return cell;
+    }
+
+    let chunk_position = get_chunk_position(position);
+    var level = 0u;
+    while level < OCCUPANCY_LEVELS && !is_cell_occupied(level + 1u, chunk_position >> vec4<u32>(level + 1u)) {
+        level += 1u;
+    }
+    cell.min = ((chunk_position >> vec4<u32>(level)) << vec4<u32>(level)) * CHUNK_SIZE;
+    cell.size = CHUNK_SIZE << level;
+    return cell;
+}
+
+// how far along the ray the next boundary of the voxel at `map_check` is along each axis
+fn get_next_boundaries(ray: Ray, map_check: vec4<i32>, step: vec4<i32>, ray_step_size_per_unit_axis: vec4<f32>) -> vec4<f32> {
+    var ray_lengths_per_axis = vec4<f32>(0.0);
     for (var i = 0u; i < 4u; i += 1u) {
-        if ray.direction[i] < 0.0 {
-            step[i] = -1;
+        if step[i] < 0 {
             ray_lengths_per_axis[i] = (ray.origin[i] - f32(map_check[i])) * ray_step_size_per_unit_axis[i];
-        } else {
-            step[i] = 1;
+        } else if step[i] > 0 {
             ray_lengths_per_axis[i] = (f32(map_check[i] + 1) - ray.origin[i]) * ray_step_size_per_unit_axis[i];
         }
     }
+    return ray_lengths_per_axis;
+}
+
+// Steps through the world one voxel at a time, but jumps across the cells that `occupancy` says are empty,
+// stopping at the first voxel that isn't made of `medium`
+fn get_intersection(ray: Ray, medium: u32) -> Hit {
+    var hit: Hit;
+    hit.hit = false;
+
+    // how far along the ray you have to go to cross one unit along each axis,
+    // computed this way so that axes the ray doesn't move along are infinite rather than NaN
+    let ray_length = length(ray.direction);
+    let ray_step_size_per_unit_axis = ray_length / abs(ray.direction);
+    var map_check = vec4<i32>(floor(ray.origin));
+    let step = vec4<i32>(sign(ray.direction));
+    if all(step == vec4<i32>(0)) {
+        return hit;
+    }
+    var ray_lengths_per_axis = get_next_boundaries(ray, map_check, step, ray_step_size_per_unit_axis);
 
     var distance = 0.0;
     while distance < camera.max_distance {
-        // TODO: find out if this is causing a black line through the middle of the screen when looking exactly forward
-        // for whatever reason, setting the initial value to 1 seems to stop the issue
+        // axes the ray doesn't move along are never stepped
         var smallest_length = 0u;
         for (var i = 0u; i < 4u; i += 1u) {
-            if step[i] != 0 && ray_lengths_per_axis[i] < ray_lengths_per_axis[smallest_length] {
+            if step[i] != 0 && (step[smallest_length] == 0 || ray_lengths_per_axis[i] < ray_lengths_per_axis[smallest_length]) {
                 smallest_length = i;
             }
         }
@@ -94,31 +325,400 @@ fn get_intersection(ray: Ray) -> Hit {
         distance = ray_lengths_per_axis[smallest_length];
         ray_lengths_per_axis[smallest_length] += ray_step_size_per_unit_axis[smallest_length];
 
-        if all(map_check >= vec4<i32>(0)) && all(map_check < vec4<i32>(CHUNK_SIZE)) {
-            let index = get_block_index(map_check);
-            let material = chunk[index].material;
-            if material != u32(-1) {
-                hit.hit = true;
-                hit.block_index = index;
-                hit.distance = distance;
-                hit.position = ray.origin + ray.direction * distance;
-                hit.normal = vec4<f32>(0.0);
-                hit.normal[smallest_length] = -f32(step[smallest_length]);
-                return hit;
+        let index = get_voxel_index(map_check);
+        var material = EMPTY_MATERIAL;
+        if index != u32(-1) {
+            material = voxels[index].material;
+        }
+        if material != medium {
+            hit.hit = true;
+            hit.block_index = index;
+            hit.material = material;
+            hit.voxel_position = map_check;
+            hit.distance = distance;
+            hit.position = ray.origin + ray.direction * (distance / ray_length);
+            hit.normal = vec4<f32>(0.0);
+            hit.normal[smallest_length] = -f32(step[smallest_length]);
+            return hit;
+        }
+
+        let cell = get_empty_cell(map_check, index);
+        if cell.size == 0 {
+            continue;
+        }
+        // move to the last voxel within the cell, so that the next step leaves it
+        let cell_max = cell.min + vec4<i32>(cell.size);
+        var exit_axis = 0u;
+        var exit_distances = vec4<f32>(0.0);
+        for (var i = 0u; i < 4u; i += 1u) {
+            if step[i] < 0 {
+                exit_distances[i] = (ray.origin[i] - f32(cell.min[i])) * ray_step_size_per_unit_axis[i];
+            } else if step[i] > 0 {
+                exit_distances[i] = (f32(cell_max[i]) - ray.origin[i]) * ray_step_size_per_unit_axis[i];
+            }
+            if step[i] != 0 && (step[exit_axis] == 0 || exit_distances[i] < exit_distances[exit_axis]) {
+                exit_axis = i;
+            }
+        }
+        let exit_position = ray.origin + ray.direction * (exit_distances[exit_axis] / ray_length);
+        for (var i = 0u; i < 4u; i += 1u) {
+            // never back, which rounding could otherwise do and then repeat forever
+            let exit_voxel = clamp(i32(floor(exit_position[i])), cell.min[i], cell_max[i] - 1);
+            if step[i] > 0 {
+                map_check[i] = max(map_check[i], exit_voxel);
+            } else if step[i] < 0 {
+                map_check[i] = min(map_check[i], exit_voxel);
             }
         }
+        if step[exit_axis] > 0 {
+            map_check[exit_axis] = cell_max[exit_axis] - 1;
+        } else {
+            map_check[exit_axis] = cell.min[exit_axis];
+        }
+        ray_lengths_per_axis = get_next_boundaries(ray, map_check, step, ray_step_size_per_unit_axis);
     }
 
     return hit;
 }
 
-fn ray_trace(ray: Ray) -> vec3<f32> {
-    let hit = get_intersection(ray);
-    if hit.hit {
-        return materials.data[chunk[hit.block_index].material].color;
+fn get_hit_color(hit: Hit) -> vec3<f32> {
+    if highlight.enabled != 0u && all(hit.voxel_position == highlight.position) {
+        // the ray hits a 3D face of the tesseract, so outline the edges of that cube
+        let local_position = hit.position - vec4<f32>(hit.voxel_position);
+        let distance_to_edge = min(local_position, vec4<f32>(1.0) - local_position);
+        // the axis along the normal is constant across the face, so leave it out
+        let distance_to_outline = select(distance_to_edge, vec4<f32>(1.0), abs(hit.normal) > vec4<f32>(0.5));
+        if any(distance_to_outline < vec4<f32>(HIGHLIGHT_OUTLINE_WIDTH)) {
+            return HIGHLIGHT_OUTLINE_COLOR;
+        }
+    }
+    let material_color = materials.data[hit.material].color;
+    let color = shade(material_color, hit.normal, is_in_shadow(hit)) + material_color * lighting.block_light * get_block_light(hit);
+    if lighting.ambient_occlusion != 0u {
+        return color * (1.0 - lighting.ambient_occlusion_strength * get_ambient_occlusion(hit));
+    }
+    return color;
+}
+
+// the light level of the voxel in front of the face, from 0 to 1
+fn get_block_light(hit: Hit) -> f32 {
+    let index = get_voxel_index(hit.voxel_position + vec4<i32>(hit.normal));
+    if index == u32(-1) {
+        return 0.0;
+    }
+    return f32(voxels[index].light) / f32(MAX_LIGHT_LEVEL);
+}
+
+// weighs the occupied neighbours of the voxel in front of the face, within the 3D hyperface,
+// by how close the hit is to them, from 0 for none to 1 for a face in a corner closed off along every axis
+fn get_ambient_occlusion(hit: Hit) -> f32 {
+    let normal = vec4<i32>(hit.normal);
+    let front = hit.voxel_position + normal;
+    let local_position = hit.position - vec4<f32>(hit.voxel_position);
+    var tangents: array<u32, 3>;
+    var tangent_count = 0u;
+    for (var axis = 0u; axis < 4u; axis += 1u) {
+        if normal[axis] == 0 {
+            tangents[tangent_count] = axis;
+            tangent_count += 1u;
+        }
+    }
+
+    var occlusion = 0.0;
+    for (var i = 0; i < 27; i += 1) {
+        // the voxel in front itself is empty, since the ray came through it
+        if i == 13 {
+            continue;
+        }
+        let offsets = vec3<i32>(i % 3, i / 3 % 3, i / 9) - vec3<i32>(1);
+        var neighbour = front;
+        var weight = 1.0;
+        for (var j = 0u; j < 3u; j += 1u) {
+            let axis = tangents[j];
+            neighbour[axis] += offsets[j];
+            let f = local_position[axis];
+            if offsets[j] == 1 {
+                weight *= f;
+            } else if offsets[j] == -1 {
+                weight *= 1.0 - f;
+            }
+        }
+        let index = get_voxel_index(neighbour);
+        if index != u32(-1) && voxels[index].material != u32(-1) {
+            occlusion += weight;
+        }
+    }
+    return occlusion / AMBIENT_OCCLUSION_TOTAL_WEIGHT;
+}
+
+// faces that point away from the sun are always in shadow, and nothing else is if shadows are off
+fn is_in_shadow(hit: Hit) -> bool {
+    if dot(hit.normal, lighting.sun_direction) <= 0.0 {
+        return true;
+    }
+    if lighting.shadows == 0u {
+        return false;
+    }
+    // start in the voxel in front of the face, so the ray can't hit the voxel it leaves
+    var ray: Ray;
+    ray.origin = hit.position + hit.normal * SECONDARY_RAY_OFFSET;
+    ray.direction = lighting.sun_direction;
+    return get_intersection(ray, EMPTY_MATERIAL).hit;
+}
+
+// lights `color` on a face pointing along `normal`
+fn shade(color: vec3<f32>, normal: vec4<f32>, in_shadow: bool) -> vec3<f32> {
+    var diffuse = max(dot(normal, lighting.sun_direction), 0.0);
+    if in_shadow {
+        diffuse = 0.0;
+    }
+    return color * (lighting.ambient + lighting.sun_intensity * diffuse);
+}
+
+// the light coming from the sky along `direction`, blending from the horizon up to the zenith and down to the ground,
+// without the sun disc
+fn get_sky_color(direction: vec4<f32>) -> vec3<f32> {
+    let up = direction.y / length(direction);
+    if up >= 0.0 {
+        return mix(environment.horizon_color, environment.zenith_color, sqrt(up));
+    }
+    return mix(environment.horizon_color, environment.ground_color, sqrt(-up));
+}
+
+// what a ray going along `direction` sees when it doesn't hit anything
+fn get_environment_color(direction: vec4<f32>) -> vec3<f32> {
+    if dot(normalize(direction), lighting.sun_direction) >= environment.sun_disc_cos {
+        return environment.sun_disc_color;
+    }
+    return get_sky_color(direction);
+}
+
+// how much of a hit `distance` away is replaced by the sky behind it
+fn get_fog(distance: f32) -> f32 {
+    if environment.fog == 0u {
+        return 0.0;
+    }
+    return 1.0 - exp(-environment.fog_density * distance);
+}
+
+// a ray still to be followed by `ray_trace`
+struct PendingRay {
+    ray: Ray,
+    // how much of the light coming back along the ray reaches the camera
+    throughput: vec3<f32>,
+    // what the ray is travelling through, so that it stops where that changes
+    medium: u32,
+    bounces: u32,
+}
+
+fn get_refractive_index(material: u32) -> f32 {
+    if material == EMPTY_MATERIAL {
+        return 1.0;
+    }
+    return materials.data[material].refractive_index;
+}
+
+fn get_reflected_ray(pending: PendingRay, hit: Hit, throughput: vec3<f32>) -> PendingRay {
+    var reflected = pending;
+    reflected.ray.origin = hit.position + hit.normal * SECONDARY_RAY_OFFSET;
+    reflected.ray.direction = reflect(pending.ray.direction, hit.normal);
+    reflected.throughput = throughput;
+    reflected.bounces += 1u;
+    return reflected;
+}
+
+// into the voxel that was hit, unless all of the light is reflected
+fn get_transmitted_ray(pending: PendingRay, hit: Hit, throughput: vec3<f32>) -> PendingRay {
+    let eta = get_refractive_index(pending.medium) / get_refractive_index(hit.material);
+    let direction = refract(pending.ray.direction, hit.normal, eta);
+    if all(direction == vec4<f32>(0.0)) {
+        return get_reflected_ray(pending, hit, throughput);
+    }
+    var transmitted: PendingRay;
+    transmitted.ray.origin = hit.position - hit.normal * SECONDARY_RAY_OFFSET;
+    transmitted.ray.direction = direction;
+    transmitted.throughput = throughput;
+    transmitted.medium = hit.material;
+    transmitted.bounces = pending.bounces + 1u;
+    return transmitted;
+}
+
+// follows the rays that are reflected off and refracted through the voxels that are hit,
+// until they've bounced `lighting.max_bounces` times
+//
+// the alpha is 1 if the ray from the camera hit anything, and 0 otherwise
+fn ray_trace(ray: Ray) -> vec4<f32> {
+    var stack: array<PendingRay, RAY_STACK_SIZE>;
+    stack[0].ray = ray;
+    stack[0].throughput = vec3<f32>(1.0);
+    stack[0].medium = EMPTY_MATERIAL;
+    stack[0].bounces = 0u;
+    var stack_size = 1;
+
+    var color = vec3<f32>(0.0);
+    var alpha = 0.0;
+    while stack_size > 0 {
+        stack_size -= 1;
+        var pending = stack[stack_size];
+        let hit = get_intersection(pending.ray, pending.medium);
+        if !hit.hit {
+            color += pending.throughput * get_environment_color(pending.ray.direction);
+            continue;
+        }
+        alpha = 1.0;
+        let can_bounce = pending.bounces < min(lighting.max_bounces, MAX_BOUNCES);
+
+        // the part of the light that the fog takes away is made up for by the sky
+        let fog = get_fog(hit.distance);
+        color += pending.throughput * fog * get_sky_color(pending.ray.direction);
+        pending.throughput *= 1.0 - fog;
+
+        // leaving a transparent voxel, which bends the ray without lighting it
+        if hit.material == EMPTY_MATERIAL {
+            if can_bounce {
+                stack[stack_size] = get_transmitted_ray(pending, hit, pending.throughput);
+                stack_size += 1;
+            }
+            continue;
+        }
+
+        let material = materials.data[hit.material];
+        let opacity = max(1.0 - material.reflectivity - material.transparency, 0.0);
+        color += pending.throughput * (get_hit_color(hit) * opacity + material.color * material.emission);
+        if !can_bounce {
+            continue;
+        }
+        if material.reflectivity > 0.0 {
+            stack[stack_size] = get_reflected_ray(pending, hit, pending.throughput * material.reflectivity);
+            stack_size += 1;
+        }
+        if material.transparency > 0.0 {
+            stack[stack_size] = get_transmitted_ray(pending, hit, pending.throughput * material.color * material.transparency);
+            stack_size += 1;
+        }
+    }
+    return vec4<f32>(color, alpha);
+}
+
+
+var<private> random_state: u32;
+
+// the PCG hash, from "Hash Functions for GPU Rendering" by Jarzynski and Olano
+fn hash(input: u32) -> u32 {
+    let state = input * 747796405u + 2891336453u;
+    let word = ((state >> ((state >> 28u) + 4u)) ^ state) * 277803737u;
+    return (word >> 22u) ^ word;
+}
+
+// a random number from 0 up to 1, from `random_state`
+fn random() -> f32 {
+    random_state = hash(random_state);
+    return f32(random_state >> 8u) / 16777216.0;
+}
+
+// a direction away from a face pointing along `normal`, more likely the closer it is to the normal,
+// from a random point in the unit ball of the three other axes projected up onto the hemisphere
+fn get_diffuse_direction(normal: vec4<f32>) -> vec4<f32> {
+    let z = random() * 2.0 - 1.0;
+    let angle = random() * 2.0 * PI;
+    let point = vec3<f32>(sqrt(1.0 - z * z) * vec2<f32>(cos(angle), sin(angle)), z) * pow(random(), 1.0 / 3.0);
+    let height = sqrt(max(1.0 - dot(point, point), 0.0));
+    var direction = normal * height;
+    var tangent = 0u;
+    for (var axis = 0u; axis < 4u; axis += 1u) {
+        if abs(normal[axis]) < 0.5 {
+            direction[axis] = point[tangent];
+            tangent += 1u;
+        }
+    }
+    return direction;
+}
+
+// follows a single ray as it bounces off in random directions, picking between the diffuse,
+// reflected and transmitted parts of each material by how much of the light they make up,
+// with light from the environment where the ray escapes, from the sun at every diffuse hit and from emissive materials
+fn path_trace_ray(ray: Ray) -> vec3<f32> {
+    var pending: PendingRay;
+    pending.ray = ray;
+    pending.throughput = vec3<f32>(1.0);
+    pending.medium = EMPTY_MATERIAL;
+    pending.bounces = 0u;
+
+    var color = vec3<f32>(0.0);
+    // the sun is already lit directly at every diffuse hit, so the rays bounced off them mustn't see it again
+    var diffuse = false;
+    loop {
+        let hit = get_intersection(pending.ray, pending.medium);
+        if !hit.hit {
+            if diffuse {
+                color += pending.throughput * get_sky_color(pending.ray.direction);
+            } else {
+                color += pending.throughput * get_environment_color(pending.ray.direction);
+            }
+            break;
+        }
+        let can_bounce = pending.bounces < min(lighting.max_bounces, MAX_BOUNCES);
+
+        let fog = get_fog(hit.distance);
+        color += pending.throughput * fog * get_sky_color(pending.ray.direction);
+        pending.throughput *= 1.0 - fog;
+
+        // leaving a transparent voxel, which bends the ray without lighting it
+        if hit.material == EMPTY_MATERIAL {
+            if !can_bounce {
+                break;
+            }
+            pending = get_transmitted_ray(pending, hit, pending.throughput);
+            continue;
+        }
+
+        let material = materials.data[hit.material];
+        let opacity = max(1.0 - material.reflectivity - material.transparency, 0.0);
+        color += pending.throughput * material.color * material.emission;
+        if opacity > 0.0 && !is_in_shadow(hit) {
+            color += pending.throughput * opacity * material.color * lighting.sun_intensity * dot(hit.normal, lighting.sun_direction);
+        }
+        if !can_bounce {
+            break;
+        }
+
+        // keeps the throughput the same on average, whichever part is picked
+        let total = opacity + material.reflectivity + material.transparency;
+        let choice = random() * total;
+        let throughput = pending.throughput * total;
+        diffuse = choice < opacity;
+        if diffuse {
+            pending.ray.origin = hit.position + hit.normal * SECONDARY_RAY_OFFSET;
+            pending.ray.direction = get_diffuse_direction(hit.normal);
+            pending.throughput = throughput * material.color;
+            pending.bounces += 1u;
+        } else if choice < opacity + material.reflectivity {
+            pending = get_reflected_ray(pending, hit, throughput);
+        } else {
+            pending = get_transmitted_ray(pending, hit, throughput * material.color);
+        }
+    }
+    return color;
+}
+
+// `normalized_coords` are in the range -1 to 1, with z going along the camera's ana axis
+// and spanning the same extent as the up axis
+fn get_camera_ray(normalized_coords: vec3<f32>, aspect: f32) -> Ray {
+    var half_extents = vec2<f32>(camera.half_extent * aspect, camera.half_extent);
+    if camera.fov_axis == FOV_AXIS_HORIZONTAL {
+        half_extents = vec2<f32>(camera.half_extent, camera.half_extent / aspect);
+    }
+    let offset = camera.right * (normalized_coords.x * half_extents.x) + camera.up * (normalized_coords.y * half_extents.y) + camera.ana * (normalized_coords.z * half_extents.y);
+
+    var ray: Ray;
+    if camera.orthographic != 0u {
+        ray.origin = camera.position + offset;
+        ray.direction = camera.forward;
     } else {
-        return vec3<f32>(0.0);
+        ray.origin = camera.position;
+        ray.direction = normalize(offset + camera.forward);
     }
+    return ray;
 }
 
 @compute
@@ -133,16 +733,104 @@ fn main(
     }
 
     let aspect = f32(size.x) / f32(size.y);
-    let theta = tan(camera.fov / 2.0);
     let uv = vec2<f32>(coords) / vec2<f32>(size);
     let normalized_uv = vec2<f32>(uv.x, 1.0 - uv.y) * 2.0 - 1.0;
 
-    var ray: Ray;
-    ray.origin = camera.position;
-    ray.direction = normalize(
-        camera.right * (normalized_uv.x * aspect * theta) + camera.up * (normalized_uv.y * theta) + camera.forward,
-    );
+    let ray = get_camera_ray(vec3<f32>(normalized_uv, 0.0), aspect);
+
+    let color = ray_trace(ray).rgb;
+    textureStore(output_texture, coords.xy, vec4<f32>(clamp(color, vec3<f32>(0.0), vec3<f32>(1.0)), 1.0));
+}
+
+@compute
+@workgroup_size(16, 16)
+fn retina(
+    @builtin(global_invocation_id) global_id: vec3<u32>,
+) {
+    let size = vec2<i32>(textureDimensions(retina_output));
+    let layers = i32(textureNumLayers(retina_output));
+    let coords = vec2<i32>(global_id.xy);
+    let layer = i32(global_id.z);
+    if coords.x >= size.x || coords.y >= size.y || layer >= layers {
+        return;
+    }
+
+    let aspect = f32(size.x) / f32(size.y);
+    let uvw = (vec3<f32>(vec3<i32>(coords, layer)) + vec3<f32>(0.0, 0.0, 0.5)) / vec3<f32>(vec2<f32>(size), f32(layers));
+    let normalized_uvw = vec3<f32>(uvw.x, 1.0 - uvw.y, uvw.z) * 2.0 - 1.0;
+
+    let ray = get_camera_ray(normalized_uvw, aspect);
 
     let color = ray_trace(ray);
+    textureStore(retina_output, coords.xy, layer, clamp(color, vec4<f32>(0.0), vec4<f32>(1.0)));
+}
+
+@compute
+@workgroup_size(16, 16)
+fn retina_composite(
+    @builtin(global_invocation_id) global_id: vec3<u32>,
+) {
+    let size = vec2<i32>(textureDimensions(output_texture));
+    let coords = vec2<i32>(global_id.xy);
+    if coords.x >= size.x || coords.y >= size.y {
+        return;
+    }
+
+    // blend front to back, treating the middle layer as the closest and moving outwards along ana and kata
+    let layers = RETINA_LAYERS;
+    let center = layers / 2;
+    var color = vec3<f32>(0.0);
+    var alpha = 0.0;
+    for (var i = 0; i < layers; i += 1) {
+        var layer = center - i / 2;
+        if i % 2 == 1 {
+            layer = center + (i + 1) / 2;
+        }
+        if layer < 0 || layer >= layers {
+            continue;
+        }
+
+        let distance_from_center = abs(f32(layer - center)) / max(f32(center), 1.0);
+        let sample = textureLoad(retina_input, coords, layer, 0);
+        let opacity = sample.a * mix(RETINA_CENTER_OPACITY, RETINA_EDGE_OPACITY, distance_from_center);
+        color += (1.0 - alpha) * opacity * sample.rgb;
+        alpha += (1.0 - alpha) * opacity;
+    }
+
+    // the environment shows through wherever the layers don't cover it
+    let aspect = f32(size.x) / f32(size.y);
+    let uv = vec2<f32>(coords) / vec2<f32>(size);
+    let normalized_uv = vec2<f32>(uv.x, 1.0 - uv.y) * 2.0 - 1.0;
+    let ray = get_camera_ray(vec3<f32>(normalized_uv, 0.0), aspect);
+    color += (1.0 - alpha) * get_environment_color(ray.direction);
+
+    textureStore(output_texture, coords.xy, vec4<f32>(color, 1.0));
+}
+
+@compute
+@workgroup_size(16, 16)
+fn path_trace(
+    @builtin(global_invocation_id) global_id: vec3<u32>,
+) {
+    let size = vec2<i32>(textureDimensions(output_texture));
+    let coords = vec2<i32>(global_id.xy);
+    if coords.x >= size.x || coords.y >= size.y {
+        return;
+    }
+    random_state = hash(global_id.x ^ hash(global_id.y ^ hash(accumulation.samples)));
+
+    // a random point within the pixel each sample, which smooths out the edges
+    let aspect = f32(size.x) / f32(size.y);
+    let uv = (vec2<f32>(coords) + vec2<f32>(random(), random())) / vec2<f32>(size);
+    let normalized_uv = vec2<f32>(uv.x, 1.0 - uv.y) * 2.0 - 1.0;
+
+    let ray = get_camera_ray(vec3<f32>(normalized_uv, 0.0), aspect);
+
+    var total = path_trace_ray(ray);
+    if accumulation.samples > 0u {
+        total += textureLoad(accumulation_input, coords, 0).rgb;
+    }
+    textureStore(accumulation_output, coords, vec4<f32>(total, 1.0));
+    let color = total / f32(accumulation.samples + 1u);
     textureStore(output_texture, coords.xy, vec4<f32>(clamp(color, vec3<f32>(0.0), vec3<f32>(1.0)), 1.0));
 }
@@ -0,0 +1,798 @@
+use eframe::wgpu::{self, include_wgsl};
+use encase::{ShaderSize, ShaderType, UniformBuffer};
+
+use crate::{
+    Camera, Environment, GpuCamera, GpuEnvironment, GpuLighting, GpuWorld, Lighting, Texture, World,
+};
+
+/// The voxel that is outlined to show what will be edited
+#[derive(ShaderType)]
+pub struct GpuHighlight {
+    position: cgmath::Vector4<i32>,
+    enabled: u32,
+}
+
+/// How many samples of `RenderMode::PathTraced` have already been added up in the accumulation texture
+#[derive(ShaderType)]
+pub struct GpuAccumulation {
+    samples: u32,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum RenderMode {
+    /// Casts rays through the forward, right and up axes of the camera
+    Perspective,
+    /// Renders the 3D cross section of the world at the camera's W position
+    Slice,
+    /// Renders a 3D image with layers spread along the camera's ana axis,
+    /// then composites the layers so geometry hidden in W shows through
+    Volumetric,
+    /// Follows rays that bounce off in random directions, lit by the sky and emissive materials,
+    /// and averages one more sample per pixel each frame for as long as nothing changes
+    PathTraced,
+}
+
+impl RenderMode {
+    /// The camera that rays are actually cast from in this mode
+    pub fn view_camera(self, camera: &Camera) -> Camera {
+        match self {
+            RenderMode::Perspective | RenderMode::Volumetric | RenderMode::PathTraced => *camera,
+            RenderMode::Slice => camera.slice(),
+        }
+    }
+}
+
+/// Must match `RETINA_LAYERS` in `ray_tracing.wgsl`
+const RETINA_LAYERS: u32 = 15;
+
+/// Renders a `World` into the main texture with the ray tracing compute shaders,
+/// without depending on a window so it can also be used offscreen
+pub struct Renderer {
+    /// Uploaded on every render, so it can be changed at any time
+    pub lighting: Lighting,
+    /// Uploaded on every render, like `lighting`
+    pub environment: Environment,
+    main_texture: Texture<'static>,
+    main_texture_bind_group_layout: wgpu::BindGroupLayout,
+    main_texture_bind_group: wgpu::BindGroup,
+    retina_texture: Texture<'static>,
+    retina_write_bind_group_layout: wgpu::BindGroupLayout,
+    retina_write_bind_group: wgpu::BindGroup,
+    retina_read_bind_group_layout: wgpu::BindGroupLayout,
+    retina_read_bind_group: wgpu::BindGroup,
+    /// Ping-ponged, with each one holding the sum of the samples so far in turn
+    accumulation_textures: [Texture<'static>; 2],
+    accumulation_bind_group_layout: wgpu::BindGroupLayout,
+    /// Reading from the accumulation texture with the same index and writing to the other one
+    accumulation_bind_groups: [wgpu::BindGroup; 2],
+    accumulation_uniform_buffer: wgpu::Buffer,
+    /// How many samples have been added up, and what they were rendered with
+    samples: u32,
+    accumulated_camera: Option<GpuCamera>,
+    accumulated_lighting: Lighting,
+    accumulated_environment: Environment,
+    camera_uniform_buffer: wgpu::Buffer,
+    highlight_uniform_buffer: wgpu::Buffer,
+    lighting_uniform_buffer: wgpu::Buffer,
+    environment_uniform_buffer: wgpu::Buffer,
+    camera_bind_group: wgpu::BindGroup,
+    gpu_world: GpuWorld,
+    ray_tracing_pipeline: wgpu::ComputePipeline,
+    retina_pipeline: wgpu::ComputePipeline,
+    retina_composite_pipeline: wgpu::ComputePipeline,
+    path_tracing_pipeline: wgpu::ComputePipeline,
+}
+
+impl Renderer {
+    pub fn new(device: &wgpu::Device) -> Self {
+        let main_texture = Texture::new(
+            device,
+            wgpu::TextureDescriptor {
+                label: Some("Main Texture"),
+                size: wgpu::Extent3d {
+                    width: 1,
+                    height: 1,
+                    depth_or_array_layers: 1,
+                },
+                mip_level_count: 1,
+                sample_count: 1,
+                dimension: wgpu::TextureDimension::D2,
+                format: wgpu::TextureFormat::Rgba8Unorm,
+                usage: wgpu::TextureUsages::TEXTURE_BINDING
+                    | wgpu::TextureUsages::STORAGE_BINDING
+                    | wgpu::TextureUsages::COPY_SRC,
+                view_formats: &[],
+            },
+        );
+        let main_texture_bind_group_layout =
+            device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+                label: Some("Main Texture Bind Group Layout"),
+                entries: &[wgpu::BindGroupLayoutEntry {
+                    binding: 0,
+                    visibility: wgpu::ShaderStages::COMPUTE,
+                    ty: wgpu::BindingType::StorageTexture {
+                        access: wgpu::StorageTextureAccess::WriteOnly,
+                        format: wgpu::TextureFormat::Rgba8Unorm,
+                        view_dimension: wgpu::TextureViewDimension::D2,
+                    },
+                    count: None,
+                }],
+            });
+        let main_texture_bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: Some("Main Texture Bind Group"),
+            layout: &main_texture_bind_group_layout,
+            entries: &[wgpu::BindGroupEntry {
+                binding: 0,
+                resource: wgpu::BindingResource::TextureView(
+                    &main_texture.create_view(&Default::default()),
+                ),
+            }],
+        });
+
+        let retina_texture = Texture::new(
+            device,
+            wgpu::TextureDescriptor {
+                label: Some("Retina Texture"),
+                size: wgpu::Extent3d {
+                    width: 1,
+                    height: 1,
+                    depth_or_array_layers: RETINA_LAYERS,
+                },
+                mip_level_count: 1,
+                sample_count: 1,
+                dimension: wgpu::TextureDimension::D2,
+                format: wgpu::TextureFormat::Rgba8Unorm,
+                usage: wgpu::TextureUsages::TEXTURE_BINDING | wgpu::TextureUsages::STORAGE_BINDING,
+                view_formats: &[],
+            },
+        );
+        let retina_write_bind_group_layout =
+            device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+                label: Some("Retina Write Bind Group Layout"),
+                entries: &[wgpu::BindGroupLayoutEntry {
+                    binding: 0,
+                    visibility: wgpu::ShaderStages::COMPUTE,
+                    ty: wgpu::BindingType::StorageTexture {
+                        access: wgpu::StorageTextureAccess::WriteOnly,
+                        format: wgpu::TextureFormat::Rgba8Unorm,
+                        view_dimension: wgpu::TextureViewDimension::D2Array,
+                    },
+                    count: None,
+                }],
+            });
+        let retina_read_bind_group_layout =
+            device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+                label: Some("Retina Read Bind Group Layout"),
+                entries: &[wgpu::BindGroupLayoutEntry {
+                    binding: 1,
+                    visibility: wgpu::ShaderStages::COMPUTE,
+                    ty: wgpu::BindingType::Texture {
+                        sample_type: wgpu::TextureSampleType::Float { filterable: false },
+                        view_dimension: wgpu::TextureViewDimension::D2Array,
+                        multisampled: false,
+                    },
+                    count: None,
+                }],
+            });
+        let (retina_write_bind_group, retina_read_bind_group) = Self::create_retina_bind_groups(
+            device,
+            &retina_texture,
+            &retina_write_bind_group_layout,
+            &retina_read_bind_group_layout,
+        );
+
+        let accumulation_textures = [0, 1].map(|_| {
+            Texture::new(
+                device,
+                wgpu::TextureDescriptor {
+                    label: Some("Accumulation Texture"),
+                    size: wgpu::Extent3d {
+                        width: 1,
+                        height: 1,
+                        depth_or_array_layers: 1,
+                    },
+                    mip_level_count: 1,
+                    sample_count: 1,
+                    dimension: wgpu::TextureDimension::D2,
+                    format: wgpu::TextureFormat::Rgba32Float,
+                    usage: wgpu::TextureUsages::TEXTURE_BINDING
+                        | wgpu::TextureUsages::STORAGE_BINDING,
+                    view_formats: &[],
+                },
+            )
+        });
+        let accumulation_uniform_buffer = device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("Accumulation Uniform Buffer"),
+            size: <GpuAccumulation as ShaderSize>::SHADER_SIZE.get(),
+            usage: wgpu::BufferUsages::COPY_DST | wgpu::BufferUsages::UNIFORM,
+            mapped_at_creation: false,
+        });
+        let accumulation_bind_group_layout =
+            device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+                label: Some("Accumulation Bind Group Layout"),
+                entries: &[
+                    wgpu::BindGroupLayoutEntry {
+                        binding: 2,
+                        visibility: wgpu::ShaderStages::COMPUTE,
+                        ty: wgpu::BindingType::StorageTexture {
+                            access: wgpu::StorageTextureAccess::WriteOnly,
+                            format: wgpu::TextureFormat::Rgba32Float,
+                            view_dimension: wgpu::TextureViewDimension::D2,
+                        },
+                        count: None,
+                    },
+                    wgpu::BindGroupLayoutEntry {
+                        binding: 3,
+                        visibility: wgpu::ShaderStages::COMPUTE,
+                        ty: wgpu::BindingType::Texture {
+                            sample_type: wgpu::TextureSampleType::Float { filterable: false },
+                            view_dimension: wgpu::TextureViewDimension::D2,
+                            multisampled: false,
+                        },
+                        count: None,
+                    },
+                    wgpu::BindGroupLayoutEntry {
+                        binding: 4,
+                        visibility: wgpu::ShaderStages::COMPUTE,
+                        ty: wgpu::BindingType::Buffer {
+                            ty: wgpu::BufferBindingType::Uniform,
+                            has_dynamic_offset: false,
+                            min_binding_size: Some(<GpuAccumulation as ShaderSize>::SHADER_SIZE),
+                        },
+                        count: None,
+                    },
+                ],
+            });
+        let accumulation_bind_groups = Self::create_accumulation_bind_groups(
+            device,
+            &accumulation_textures,
+            &accumulation_bind_group_layout,
+            &accumulation_uniform_buffer,
+        );
+
+        let camera_uniform_buffer = device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("Camera Uniform Buffer"),
+            size: <GpuCamera as ShaderSize>::SHADER_SIZE.get(),
+            usage: wgpu::BufferUsages::COPY_DST | wgpu::BufferUsages::UNIFORM,
+            mapped_at_creation: false,
+        });
+        let highlight_uniform_buffer = device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("Highlight Uniform Buffer"),
+            size: <GpuHighlight as ShaderSize>::SHADER_SIZE.get(),
+            usage: wgpu::BufferUsages::COPY_DST | wgpu::BufferUsages::UNIFORM,
+            mapped_at_creation: false,
+        });
+        let lighting_uniform_buffer = device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("Lighting Uniform Buffer"),
+            size: <GpuLighting as ShaderSize>::SHADER_SIZE.get(),
+            usage: wgpu::BufferUsages::COPY_DST | wgpu::BufferUsages::UNIFORM,
+            mapped_at_creation: false,
+        });
+        let environment_uniform_buffer = device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("Environment Uniform Buffer"),
+            size: <GpuEnvironment as ShaderSize>::SHADER_SIZE.get(),
+            usage: wgpu::BufferUsages::COPY_DST | wgpu::BufferUsages::UNIFORM,
+            mapped_at_creation: false,
+        });
+        let camera_bind_group_layout =
+            device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+                label: Some("Camera Bind Group Layout"),
+                entries: &[
+                    wgpu::BindGroupLayoutEntry {
+                        binding: 0,
+                        visibility: wgpu::ShaderStages::COMPUTE,
+                        ty: wgpu::BindingType::Buffer {
+                            ty: wgpu::BufferBindingType::Uniform,
+                            has_dynamic_offset: false,
+                            min_binding_size: Some(<GpuCamera as ShaderSize>::SHADER_SIZE),
+                        },
+                        count: None,
+                    },
+                    wgpu::BindGroupLayoutEntry {
+                        binding: 1,
+                        visibility: wgpu::ShaderStages::COMPUTE,
+                        ty: wgpu::BindingType::Buffer {
+                            ty: wgpu::BufferBindingType::Uniform,
+                            has_dynamic_offset: false,
+                            min_binding_size: Some(<GpuHighlight as ShaderSize>::SHADER_SIZE),
+                        },
+                        count: None,
+                    },
+                    wgpu::BindGroupLayoutEntry {
+                        binding: 2,
+                        visibility: wgpu::ShaderStages::COMPUTE,
+                        ty: wgpu::BindingType::Buffer {
+                            ty: wgpu::BufferBindingType::Uniform,
+                            has_dynamic_offset: false,
+                            min_binding_size: Some(<GpuLighting as ShaderSize>::SHADER_SIZE),
+                        },
+                        count: None,
+                    },
+                    wgpu::BindGroupLayoutEntry {
+                        binding: 3,
+                        visibility: wgpu::ShaderStages::COMPUTE,
+                        ty: wgpu::BindingType::Buffer {
+                            ty: wgpu::BufferBindingType::Uniform,
+                            has_dynamic_offset: false,
+                            min_binding_size: Some(<GpuEnvironment as ShaderSize>::SHADER_SIZE),
+                        },
+                        count: None,
+                    },
+                ],
+            });
+        let camera_bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: Some("Camera Bind Group"),
+            layout: &camera_bind_group_layout,
+            entries: &[
+                wgpu::BindGroupEntry {
+                    binding: 0,
+                    resource: wgpu::BindingResource::Buffer(wgpu::BufferBinding {
+                        buffer: &camera_uniform_buffer,
+                        offset: 0,
+                        size: Some(<GpuCamera as ShaderSize>::SHADER_SIZE),
+                    }),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 1,
+                    resource: wgpu::BindingResource::Buffer(wgpu::BufferBinding {
+                        buffer: &highlight_uniform_buffer,
+                        offset: 0,
+                        size: Some(<GpuHighlight as ShaderSize>::SHADER_SIZE),
+                    }),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 2,
+                    resource: wgpu::BindingResource::Buffer(wgpu::BufferBinding {
+                        buffer: &lighting_uniform_buffer,
+                        offset: 0,
+                        size: Some(<GpuLighting as ShaderSize>::SHADER_SIZE),
+                    }),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 3,
+                    resource: wgpu::BindingResource::Buffer(wgpu::BufferBinding {
+                        buffer: &environment_uniform_buffer,
+                        offset: 0,
+                        size: Some(<GpuEnvironment as ShaderSize>::SHADER_SIZE),
+                    }),
+                },
+            ],
+        });
+
+        let gpu_world = GpuWorld::new(device);
+
+        let ray_tracing_shader = device.create_shader_module(include_wgsl!("./ray_tracing.wgsl"));
+        let ray_tracing_pipeline_layout =
+            device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+                label: Some("Ray Tracing Pipeline Layout"),
+                bind_group_layouts: &[
+                    &main_texture_bind_group_layout,
+                    &camera_bind_group_layout,
+                    &gpu_world.bind_group_layout,
+                ],
+                push_constant_ranges: &[],
+            });
+        let ray_tracing_pipeline =
+            device.create_compute_pipeline(&wgpu::ComputePipelineDescriptor {
+                label: Some("Ray Tracing Pipeline"),
+                layout: Some(&ray_tracing_pipeline_layout),
+                module: &ray_tracing_shader,
+                entry_point: "main",
+            });
+
+        let retina_pipeline_layout =
+            device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+                label: Some("Retina Pipeline Layout"),
+                bind_group_layouts: &[
+                    &main_texture_bind_group_layout,
+                    &camera_bind_group_layout,
+                    &gpu_world.bind_group_layout,
+                    &retina_write_bind_group_layout,
+                ],
+                push_constant_ranges: &[],
+            });
+        let retina_pipeline = device.create_compute_pipeline(&wgpu::ComputePipelineDescriptor {
+            label: Some("Retina Pipeline"),
+            layout: Some(&retina_pipeline_layout),
+            module: &ray_tracing_shader,
+            entry_point: "retina",
+        });
+        let retina_composite_pipeline_layout =
+            device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+                label: Some("Retina Composite Pipeline Layout"),
+                bind_group_layouts: &[
+                    &main_texture_bind_group_layout,
+                    &camera_bind_group_layout,
+                    &gpu_world.bind_group_layout,
+                    &retina_read_bind_group_layout,
+                ],
+                push_constant_ranges: &[],
+            });
+        let retina_composite_pipeline =
+            device.create_compute_pipeline(&wgpu::ComputePipelineDescriptor {
+                label: Some("Retina Composite Pipeline"),
+                layout: Some(&retina_composite_pipeline_layout),
+                module: &ray_tracing_shader,
+                entry_point: "retina_composite",
+            });
+        let path_tracing_pipeline_layout =
+            device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+                label: Some("Path Tracing Pipeline Layout"),
+                bind_group_layouts: &[
+                    &main_texture_bind_group_layout,
+                    &camera_bind_group_layout,
+                    &gpu_world.bind_group_layout,
+                    &accumulation_bind_group_layout,
+                ],
+                push_constant_ranges: &[],
+            });
+        let path_tracing_pipeline =
+            device.create_compute_pipeline(&wgpu::ComputePipelineDescriptor {
+                label: Some("Path Tracing Pipeline"),
+                layout: Some(&path_tracing_pipeline_layout),
+                module: &ray_tracing_shader,
+                entry_point: "path_trace",
+            });
+
+        Self {
+            lighting: Lighting::default(),
+            environment: Environment::default(),
+            main_texture,
+            main_texture_bind_group_layout,
+            main_texture_bind_group,
+            retina_texture,
+            retina_write_bind_group_layout,
+            retina_write_bind_group,
+            retina_read_bind_group_layout,
+            retina_read_bind_group,
+            accumulation_textures,
+            accumulation_bind_group_layout,
+            accumulation_bind_groups,
+            accumulation_uniform_buffer,
+            samples: 0,
+            accumulated_camera: None,
+            accumulated_lighting: Lighting::default(),
+            accumulated_environment: Environment::default(),
+            camera_uniform_buffer,
+            highlight_uniform_buffer,
+            lighting_uniform_buffer,
+            environment_uniform_buffer,
+            camera_bind_group,
+            gpu_world,
+            ray_tracing_pipeline,
+            retina_pipeline,
+            retina_composite_pipeline,
+            path_tracing_pipeline,
+        }
+    }
+
+    fn create_accumulation_bind_groups(
+        device: &wgpu::Device,
+        accumulation_textures: &[Texture<'static>; 2],
+        accumulation_bind_group_layout: &wgpu::BindGroupLayout,
+        accumulation_uniform_buffer: &wgpu::Buffer,
+    ) -> [wgpu::BindGroup; 2] {
+        let views = accumulation_textures
+            .each_ref()
+            .map(|texture| texture.create_view(&Default::default()));
+        [0, 1].map(|input| {
+            device.create_bind_group(&wgpu::BindGroupDescriptor {
+                label: Some("Accumulation Bind Group"),
+                layout: accumulation_bind_group_layout,
+                entries: &[
+                    wgpu::BindGroupEntry {
+                        binding: 2,
+                        resource: wgpu::BindingResource::TextureView(&views[1 - input]),
+                    },
+                    wgpu::BindGroupEntry {
+                        binding: 3,
+                        resource: wgpu::BindingResource::TextureView(&views[input]),
+                    },
+                    wgpu::BindGroupEntry {
+                        binding: 4,
+                        resource: wgpu::BindingResource::Buffer(wgpu::BufferBinding {
+                            buffer: accumulation_uniform_buffer,
+                            offset: 0,
+                            size: Some(<GpuAccumulation as ShaderSize>::SHADER_SIZE),
+                        }),
+                    },
+                ],
+            })
+        })
+    }
+
+    fn create_retina_bind_groups(
+        device: &wgpu::Device,
+        retina_texture: &wgpu::Texture,
+        retina_write_bind_group_layout: &wgpu::BindGroupLayout,
+        retina_read_bind_group_layout: &wgpu::BindGroupLayout,
+    ) -> (wgpu::BindGroup, wgpu::BindGroup) {
+        let view = retina_texture.create_view(&wgpu::TextureViewDescriptor {
+            dimension: Some(wgpu::TextureViewDimension::D2Array),
+            ..Default::default()
+        });
+        let write_bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: Some("Retina Write Bind Group"),
+            layout: retina_write_bind_group_layout,
+            entries: &[wgpu::BindGroupEntry {
+                binding: 0,
+                resource: wgpu::BindingResource::TextureView(&view),
+            }],
+        });
+        let read_bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: Some("Retina Read Bind Group"),
+            layout: retina_read_bind_group_layout,
+            entries: &[wgpu::BindGroupEntry {
+                binding: 1,
+                resource: wgpu::BindingResource::TextureView(&view),
+            }],
+        });
+        (write_bind_group, read_bind_group)
+    }
+
+    pub fn main_texture(&self) -> &Texture<'static> {
+        &self.main_texture
+    }
+
+    pub fn gpu_world(&self) -> &GpuWorld {
+        &self.gpu_world
+    }
+
+    /// How many samples per pixel `RenderMode::PathTraced` has averaged so far
+    pub fn samples(&self) -> u32 {
+        self.samples
+    }
+
+    /// Returns whether the main texture had to be recreated, any views of it will need to be recreated too
+    pub fn resize(&mut self, device: &wgpu::Device, size: cgmath::Vector2<u32>) -> bool {
+        let size = cgmath::vec2(size.x.max(1), size.y.max(1));
+        let resized = self.main_texture.resize(device, size);
+        if resized {
+            self.main_texture_bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+                label: Some("Main Texture Bind Group"),
+                layout: &self.main_texture_bind_group_layout,
+                entries: &[wgpu::BindGroupEntry {
+                    binding: 0,
+                    resource: wgpu::BindingResource::TextureView(
+                        &self.main_texture.create_view(&Default::default()),
+                    ),
+                }],
+            });
+        }
+        if self.retina_texture.resize(device, size) {
+            (self.retina_write_bind_group, self.retina_read_bind_group) =
+                Self::create_retina_bind_groups(
+                    device,
+                    &self.retina_texture,
+                    &self.retina_write_bind_group_layout,
+                    &self.retina_read_bind_group_layout,
+                );
+        }
+        let mut accumulation_resized = false;
+        for texture in &mut self.accumulation_textures {
+            accumulation_resized |= texture.resize(device, size);
+        }
+        if accumulation_resized {
+            self.accumulation_bind_groups = Self::create_accumulation_bind_groups(
+                device,
+                &self.accumulation_textures,
+                &self.accumulation_bind_group_layout,
+                &self.accumulation_uniform_buffer,
+            );
+            self.samples = 0;
+        }
+        resized
+    }
+
+    /// Renders `world` into the main texture, uploading any changes to it first
+    ///
+    /// `camera` should already be the view camera for `render_mode`
+    pub fn render(
+        &mut self,
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        world: &mut World,
+        camera: &Camera,
+        render_mode: RenderMode,
+        highlight: Option<cgmath::Vector4<i32>>,
+    ) {
+        // Upload camera
+        let gpu_camera = camera.gpu();
+        {
+            let mut uniform_buffer =
+                UniformBuffer::new([0; <GpuCamera as ShaderSize>::SHADER_SIZE.get() as _]);
+            uniform_buffer.write(&gpu_camera).unwrap();
+            let buffer = uniform_buffer.into_inner();
+            queue.write_buffer(&self.camera_uniform_buffer, 0, &buffer);
+        }
+
+        // Upload lighting
+        {
+            let mut uniform_buffer =
+                UniformBuffer::new([0; <GpuLighting as ShaderSize>::SHADER_SIZE.get() as _]);
+            uniform_buffer.write(&self.lighting.gpu()).unwrap();
+            let buffer = uniform_buffer.into_inner();
+            queue.write_buffer(&self.lighting_uniform_buffer, 0, &buffer);
+        }
+
+        // Upload environment
+        {
+            let mut uniform_buffer =
+                UniformBuffer::new([0; <GpuEnvironment as ShaderSize>::SHADER_SIZE.get() as _]);
+            uniform_buffer.write(&self.environment.gpu()).unwrap();
+            let buffer = uniform_buffer.into_inner();
+            queue.write_buffer(&self.environment_uniform_buffer, 0, &buffer);
+        }
+
+        // Upload highlight
+        {
+            let mut uniform_buffer =
+                UniformBuffer::new([0; <GpuHighlight as ShaderSize>::SHADER_SIZE.get() as _]);
+            uniform_buffer
+                .write(&GpuHighlight {
+                    position: highlight.unwrap_or(cgmath::vec4(0, 0, 0, 0)),
+                    enabled: highlight.is_some() as u32,
+                })
+                .unwrap();
+            let buffer = uniform_buffer.into_inner();
+            queue.write_buffer(&self.highlight_uniform_buffer, 0, &buffer);
+        }
+
+        // Upload materials and voxels
+        let world_changed = self.gpu_world.update(device, queue, world);
+
+        // Start accumulating again whenever anything that the samples depend on changes
+        if render_mode != RenderMode::PathTraced
+            || world_changed
+            || self.accumulated_camera != Some(gpu_camera)
+            || self.accumulated_lighting != self.lighting
+            || self.accumulated_environment != self.environment
+        {
+            self.samples = 0;
+            self.accumulated_camera = Some(gpu_camera);
+            self.accumulated_lighting = self.lighting;
+            self.accumulated_environment = self.environment;
+        }
+
+        // Upload accumulation
+        if render_mode == RenderMode::PathTraced {
+            let mut uniform_buffer =
+                UniformBuffer::new([0; <GpuAccumulation as ShaderSize>::SHADER_SIZE.get() as _]);
+            uniform_buffer
+                .write(&GpuAccumulation {
+                    samples: self.samples,
+                })
+                .unwrap();
+            let buffer = uniform_buffer.into_inner();
+            queue.write_buffer(&self.accumulation_uniform_buffer, 0, &buffer);
+        }
+
+        // Submit ray tracing commands
+        {
+            let mut command_encoder =
+                device.create_command_encoder(&wgpu::CommandEncoderDescriptor {
+                    label: Some("Ray Tracing Encoder"),
+                });
+            const WORKGROUPS_SIZE: cgmath::Vector2<u32> = cgmath::vec2(16, 16);
+            let workgroups = {
+                let size = self.main_texture.size();
+                cgmath::vec2(
+                    size.width.div_ceil(WORKGROUPS_SIZE.x),
+                    size.height.div_ceil(WORKGROUPS_SIZE.y),
+                )
+            };
+            match render_mode {
+                RenderMode::Perspective | RenderMode::Slice => {
+                    // Compute Pass
+                    let mut compute_pass =
+                        command_encoder.begin_compute_pass(&wgpu::ComputePassDescriptor {
+                            label: Some("Ray Tracing Compute Pass"),
+                        });
+                    compute_pass.set_pipeline(&self.ray_tracing_pipeline);
+                    compute_pass.set_bind_group(0, &self.main_texture_bind_group, &[]);
+                    compute_pass.set_bind_group(1, &self.camera_bind_group, &[]);
+                    compute_pass.set_bind_group(2, &self.gpu_world.bind_group, &[]);
+                    compute_pass.dispatch_workgroups(workgroups.x, workgroups.y, 1);
+                }
+                RenderMode::Volumetric => {
+                    // Retina Compute Pass
+                    {
+                        let mut compute_pass =
+                            command_encoder.begin_compute_pass(&wgpu::ComputePassDescriptor {
+                                label: Some("Retina Compute Pass"),
+                            });
+                        compute_pass.set_pipeline(&self.retina_pipeline);
+                        compute_pass.set_bind_group(0, &self.main_texture_bind_group, &[]);
+                        compute_pass.set_bind_group(1, &self.camera_bind_group, &[]);
+                        compute_pass.set_bind_group(2, &self.gpu_world.bind_group, &[]);
+                        compute_pass.set_bind_group(3, &self.retina_write_bind_group, &[]);
+                        compute_pass.dispatch_workgroups(workgroups.x, workgroups.y, RETINA_LAYERS);
+                    }
+                    // Retina Composite Compute Pass
+                    {
+                        let mut compute_pass =
+                            command_encoder.begin_compute_pass(&wgpu::ComputePassDescriptor {
+                                label: Some("Retina Composite Compute Pass"),
+                            });
+                        compute_pass.set_pipeline(&self.retina_composite_pipeline);
+                        compute_pass.set_bind_group(0, &self.main_texture_bind_group, &[]);
+                        compute_pass.set_bind_group(1, &self.camera_bind_group, &[]);
+                        compute_pass.set_bind_group(2, &self.gpu_world.bind_group, &[]);
+                        compute_pass.set_bind_group(3, &self.retina_read_bind_group, &[]);
+                        compute_pass.dispatch_workgroups(workgroups.x, workgroups.y, 1);
+                    }
+                }
+                RenderMode::PathTraced => {
+                    // Path Tracing Compute Pass
+                    let mut compute_pass =
+                        command_encoder.begin_compute_pass(&wgpu::ComputePassDescriptor {
+                            label: Some("Path Tracing Compute Pass"),
+                        });
+                    compute_pass.set_pipeline(&self.path_tracing_pipeline);
+                    compute_pass.set_bind_group(0, &self.main_texture_bind_group, &[]);
+                    compute_pass.set_bind_group(1, &self.camera_bind_group, &[]);
+                    compute_pass.set_bind_group(2, &self.gpu_world.bind_group, &[]);
+                    compute_pass.set_bind_group(
+                        3,
+                        &self.accumulation_bind_groups[self.samples as usize % 2],
+                        &[],
+                    );
+                    compute_pass.dispatch_workgroups(workgroups.x, workgroups.y, 1);
+                }
+            }
+            queue.submit([command_encoder.finish()]);
+        }
+        if render_mode == RenderMode::PathTraced {
+            self.samples += 1;
+        }
+    }
+
+    /// Copies the main texture back to the CPU, blocking until it is done
+    ///
+    /// Returns the image as tightly packed RGBA8 rows, starting at the top
+    pub fn read_main_texture(&self, device: &wgpu::Device, queue: &wgpu::Queue) -> Vec<u8> {
+        let size = self.main_texture.size();
+        let unpadded_bytes_per_row = size.width * 4;
+        let padded_bytes_per_row = unpadded_bytes_per_row
+            .div_ceil(wgpu::COPY_BYTES_PER_ROW_ALIGNMENT)
+            * wgpu::COPY_BYTES_PER_ROW_ALIGNMENT;
+
+        let readback_buffer = device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("Main Texture Readback Buffer"),
+            size: padded_bytes_per_row as wgpu::BufferAddress * size.height as wgpu::BufferAddress,
+            usage: wgpu::BufferUsages::COPY_DST | wgpu::BufferUsages::MAP_READ,
+            mapped_at_creation: false,
+        });
+
+        let mut command_encoder = device.create_command_encoder(&wgpu::CommandEncoderDescriptor {
+            label: Some("Main Texture Readback Encoder"),
+        });
+        command_encoder.copy_texture_to_buffer(
+            self.main_texture.as_image_copy(),
+            wgpu::ImageCopyBuffer {
+                buffer: &readback_buffer,
+                layout: wgpu::ImageDataLayout {
+                    offset: 0,
+                    bytes_per_row: Some(padded_bytes_per_row),
+                    rows_per_image: Some(size.height),
+                },
+            },
+            wgpu::Extent3d {
+                depth_or_array_layers: 1,
+                ..size
+            },
+        );
+        queue.submit([command_encoder.finish()]);
+
+        let slice = readback_buffer.slice(..);
+        slice.map_async(wgpu::MapMode::Read, |result| result.unwrap());
+        device.poll(wgpu::Maintain::Wait);
+
+        let data = slice.get_mapped_range();
+        let mut image = Vec::with_capacity((unpadded_bytes_per_row * size.height) as usize);
+        for row in data.chunks(padded_bytes_per_row as usize) {
+            image.extend_from_slice(&row[..unpadded_bytes_per_row as usize]);
+        }
+        image
+    }
+}
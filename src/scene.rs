@@ -0,0 +1,175 @@
+use serde::{Deserialize, Serialize};
+use std::io;
+
+use crate::{Camera, FovAxis, Material, Projection, ProjectionMode, RotationPlane, Voxel, World};
+
+/// A hand written description of a world and a camera looking at it, stored as RON
+///
+/// ```ron
+/// Scene(
+///     camera: (position: (0.5, 2.0, -3.0, 0.5), rotations: [(YZ, -20.0)]),
+///     materials: [(name: "stone", color: (0.5, 0.5, 0.5))],
+///     regions: [
+///         Box(min: (-4, -1, -4, -4), max: (3, -1, 3, 3), material: "stone"),
+///         Clear(min: (0, -1, 0, 0), max: (0, -1, 0, 0)),
+///     ],
+/// )
+/// ```
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct Scene {
+    #[serde(default)]
+    pub camera: SceneCamera,
+    /// Become the world's materials in order, so the first one has the id 0
+    #[serde(default)]
+    pub materials: Vec<SceneMaterial>,
+    /// Applied in order, so later regions overwrite earlier ones
+    #[serde(default)]
+    pub regions: Vec<SceneRegion>,
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct SceneMaterial {
+    /// What regions refer to the material by
+    pub name: String,
+    pub color: [f32; 3],
+    #[serde(default)]
+    pub reflectivity: f32,
+    #[serde(default)]
+    pub transparency: f32,
+    #[serde(default = "default_refractive_index")]
+    pub refractive_index: f32,
+    #[serde(default)]
+    pub emission: f32,
+}
+
+fn default_refractive_index() -> f32 {
+    Material::new(cgmath::vec3(0.0, 0.0, 0.0)).refractive_index
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub enum SceneRegion {
+    Voxel {
+        position: [i32; 4],
+        material: String,
+    },
+    /// Every voxel from `min` to `max`, both inclusive
+    Box {
+        min: [i32; 4],
+        max: [i32; 4],
+        material: String,
+    },
+    /// Empties every voxel from `min` to `max`, both inclusive
+    Clear { min: [i32; 4], max: [i32; 4] },
+}
+
+/// Every field is optional and defaults to the same as `Camera::default`
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct SceneCamera {
+    pub position: [f32; 4],
+    /// Rotations in degrees, applied in order starting from looking along +Z with +Y up
+    pub rotations: Vec<(RotationPlane, f32)>,
+    pub projection: ProjectionMode,
+    /// In degrees
+    pub fov: f32,
+    pub fov_axis: FovAxis,
+    pub orthographic_size: f32,
+    pub max_distance: f32,
+}
+
+impl SceneCamera {
+    pub fn camera(&self) -> Camera {
+        let mut camera = Camera {
+            position: self.position.into(),
+            projection: Projection {
+                mode: self.projection,
+                fov: cgmath::Deg(self.fov),
+                fov_axis: self.fov_axis,
+                orthographic_size: self.orthographic_size,
+            },
+            max_distance: self.max_distance,
+            ..Default::default()
+        };
+        for &(plane, angle) in &self.rotations {
+            camera.rotate(plane, cgmath::Rad::from(cgmath::Deg(angle)).0);
+        }
+        camera.orthonormalize();
+        camera
+    }
+}
+
+impl Default for SceneCamera {
+    fn default() -> Self {
+        let camera = Camera::default();
+        Self {
+            position: camera.position.into(),
+            rotations: Vec::new(),
+            projection: camera.projection.mode,
+            fov: camera.projection.fov.0,
+            fov_axis: camera.projection.fov_axis,
+            orthographic_size: camera.projection.orthographic_size,
+            max_distance: camera.max_distance,
+        }
+    }
+}
+
+impl Scene {
+    pub fn parse(source: &str) -> io::Result<Self> {
+        ron::from_str(source).map_err(|error| io::Error::new(io::ErrorKind::InvalidData, error))
+    }
+
+    pub fn load(path: impl AsRef<std::path::Path>) -> io::Result<Self> {
+        Self::parse(&std::fs::read_to_string(path)?)
+    }
+
+    /// Fails if a region refers to a material that isn't in `materials`
+    pub fn world(&self) -> io::Result<World> {
+        let mut world = World::new();
+        for material in &self.materials {
+            world.add_material(Material {
+                reflectivity: material.reflectivity,
+                transparency: material.transparency,
+                refractive_index: material.refractive_index,
+                emission: material.emission,
+                ..Material::new(material.color.into())
+            });
+        }
+
+        let voxel = |name: &str| {
+            self.materials
+                .iter()
+                .position(|material| material.name == name)
+                .map(|id| Voxel::new(id as u32))
+                .ok_or_else(|| {
+                    io::Error::new(
+                        io::ErrorKind::InvalidData,
+                        format!("unknown material `{name}`"),
+                    )
+                })
+        };
+        for region in &self.regions {
+            match region {
+                SceneRegion::Voxel { position, material } => {
+                    world.set_voxel((*position).into(), voxel(material)?);
+                }
+                SceneRegion::Box { min, max, material } => {
+                    fill(&mut world, *min, *max, voxel(material)?);
+                }
+                SceneRegion::Clear { min, max } => fill(&mut world, *min, *max, Voxel::EMPTY),
+            }
+        }
+        Ok(world)
+    }
+}
+
+fn fill(world: &mut World, min: [i32; 4], max: [i32; 4], voxel: Voxel) {
+    for w in min[3]..=max[3] {
+        for z in min[2]..=max[2] {
+            for y in min[1]..=max[1] {
+                for x in min[0]..=max[0] {
+                    world.set_voxel(cgmath::vec4(x, y, z, w), voxel);
+                }
+            }
+        }
+    }
+}
@@ -38,6 +38,66 @@ impl<'a> StorageBuffer<'a> {
         queue.write_buffer(&self.buffer, 0, data);
         reallocated
     }
+
+    /// Makes the buffer at least `size` bytes, keeping its contents by copying them into the new buffer on the GPU
+    ///
+    /// The buffer has to have been created with `COPY_SRC` and `COPY_DST` usages.
+    /// Returns whether the buffer had to reallocate
+    pub fn grow(
+        &mut self,
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        size: wgpu::BufferAddress,
+    ) -> bool {
+        if self.buffer_descriptor.size >= size {
+            return false;
+        }
+        self.set_size(device, queue, size);
+        true
+    }
+
+    /// Reallocates the buffer with exactly `size` bytes, keeping as much of its contents as fits
+    ///
+    /// The buffer has to have been created with `COPY_SRC` and `COPY_DST` usages
+    pub fn set_size(
+        &mut self,
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        size: wgpu::BufferAddress,
+    ) {
+        assert!(self
+            .buffer_descriptor
+            .usage
+            .contains(wgpu::BufferUsages::COPY_SRC | wgpu::BufferUsages::COPY_DST));
+
+        let kept_size = self.buffer_descriptor.size.min(size);
+        self.buffer_descriptor.size = size;
+        let old_buffer = std::mem::replace(
+            &mut self.buffer,
+            device.create_buffer(&self.buffer_descriptor),
+        );
+
+        let mut encoder = device.create_command_encoder(&wgpu::CommandEncoderDescriptor {
+            label: Some("Storage Buffer Resize Encoder"),
+        });
+        encoder.copy_buffer_to_buffer(&old_buffer, 0, &self.buffer, 0, kept_size);
+        // writes already queued for the old buffer happen before this submission, so they are copied too
+        queue.submit([encoder.finish()]);
+    }
+
+    /// Overwrites part of the buffer, leaving the rest as it was
+    ///
+    /// The range has to fit within the current size of the buffer,
+    /// and `offset` and the length of `data` have to be multiples of `wgpu::COPY_BUFFER_ALIGNMENT`
+    pub fn write_range(&self, queue: &wgpu::Queue, offset: wgpu::BufferAddress, data: &[u8]) {
+        assert!(
+            offset + data.len() as wgpu::BufferAddress <= self.buffer_descriptor.size,
+            "write of {} bytes at {offset} is outside of the buffer of size {}",
+            data.len(),
+            self.buffer_descriptor.size,
+        );
+        queue.write_buffer(&self.buffer, offset, data);
+    }
 }
 
 impl std::ops::Deref for StorageBuffer<'_> {
@@ -0,0 +1,251 @@
+use rand_chacha::rand_core::{RngCore, SeedableRng};
+use rand_chacha::ChaCha8Rng;
+
+use crate::{Chunk, Material, Voxel, World, CHUNK_SIZE};
+
+/// Value noise over any number of dimensions, smoothly interpolating random values at integer lattice points
+struct Noise {
+    permutation: [u8; 256],
+}
+
+impl Noise {
+    /// Shuffles the lattice values with a Fisher-Yates shuffle of its own rather than `SliceRandom::shuffle`,
+    /// which doesn't promise to pick the same order from the same random numbers across versions of `rand`
+    fn new(rng: &mut impl RngCore) -> Self {
+        let mut permutation = [0; 256];
+        for (i, value) in permutation.iter_mut().enumerate() {
+            *value = i as u8;
+        }
+        for i in (1..permutation.len()).rev() {
+            let j = rng.next_u32() as usize % (i + 1);
+            permutation.swap(i, j);
+        }
+        Self { permutation }
+    }
+
+    /// Returns a value in the range -1 to 1
+    fn sample<const N: usize>(&self, position: [f32; N]) -> f32 {
+        let cell = position.map(|x| x.floor());
+        let fade = std::array::from_fn::<f32, N, _>(|i| {
+            let t = position[i] - cell[i];
+            t * t * (3.0 - 2.0 * t)
+        });
+
+        let mut total = 0.0;
+        for corner in 0..1 << N {
+            let mut weight = 1.0;
+            let mut hash = 0u8;
+            for i in 0..N {
+                let offset = (corner >> i) & 1;
+                weight *= if offset == 1 { fade[i] } else { 1.0 - fade[i] };
+                let lattice = (cell[i] as i32).wrapping_add(offset) as u8;
+                hash = self.permutation[hash.wrapping_add(lattice) as usize];
+            }
+            total += weight * (hash as f32 / 255.0 * 2.0 - 1.0);
+        }
+        total
+    }
+
+    /// Sums `octaves` layers of noise, each at double the frequency and half the amplitude of the last,
+    /// returning a value in the range -1 to 1
+    fn fractal<const N: usize>(&self, position: [f32; N], octaves: u32) -> f32 {
+        let mut total = 0.0;
+        let mut amplitude = 1.0;
+        let mut frequency = 1.0;
+        let mut max = 0.0;
+        for octave in 0..octaves {
+            // shift each octave so the lattice points of different octaves don't line up
+            let offset = octave as f32 * 17.31;
+            total += amplitude * self.sample(position.map(|x| x * frequency + offset));
+            max += amplitude;
+            amplitude *= 0.5;
+            frequency *= 2.0;
+        }
+        total / max
+    }
+}
+
+/// The ids of the materials that generated terrain is made of
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct TerrainMaterials {
+    pub grass: u32,
+    pub dirt: u32,
+    pub stone: u32,
+    pub sand: u32,
+    pub snow: u32,
+}
+
+impl TerrainMaterials {
+    /// Adds a material for each kind of terrain to `world`
+    pub fn add_to(world: &mut World) -> Self {
+        let mut add = |r, g, b| world.add_material(Material::new(cgmath::vec3(r, g, b)));
+        Self {
+            grass: add(0.3, 0.65, 0.2),
+            dirt: add(0.45, 0.3, 0.15),
+            stone: add(0.5, 0.5, 0.5),
+            sand: add(0.85, 0.8, 0.5),
+            snow: add(0.95, 0.95, 1.0),
+        }
+    }
+}
+
+/// What the top layers of terrain are made of, chosen by low frequency noise over X, Z and W
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Biome {
+    Grassland,
+    Desert,
+    /// Grassland that is above `TerrainGenerator::snow_height`
+    Mountains,
+}
+
+/// Generates terrain from a seed, always producing the same voxels for the same seed and settings
+///
+/// The ground is a heightmap over X, Z and W with Y as up,
+/// with caves carved out of it wherever 4D density noise is high enough
+pub struct TerrainGenerator {
+    seed: u64,
+    height_noise: Noise,
+    biome_noise: Noise,
+    cave_noise: Noise,
+    pub materials: TerrainMaterials,
+    /// The Y coordinate that the surface varies around
+    pub base_height: i32,
+    /// How far the surface can go above or below `base_height`
+    pub height_amplitude: f32,
+    /// The horizontal distance over which the height changes, in voxels
+    pub height_scale: f32,
+    /// The horizontal distance over which the biome changes, in voxels
+    pub biome_scale: f32,
+    /// Surfaces at or above this are covered in snow
+    pub snow_height: i32,
+    /// The distance over which the cave density changes, in voxels
+    pub cave_scale: f32,
+    /// Voxels where the cave density is above this are empty, 1 disables caves
+    pub cave_threshold: f32,
+    /// How many voxels below the surface caves start, so they don't riddle the surface with holes
+    pub cave_depth: i32,
+}
+
+impl TerrainGenerator {
+    pub fn new(seed: u64, materials: TerrainMaterials) -> Self {
+        // unlike `StdRng`, ChaCha8 gives the same numbers for the same seed in every version,
+        // and the seed is laid out here rather than by `seed_from_u64` for the same reason
+        let mut key = [0; 32];
+        key[..8].copy_from_slice(&seed.to_le_bytes());
+        let mut rng = ChaCha8Rng::from_seed(key);
+        Self {
+            seed,
+            height_noise: Noise::new(&mut rng),
+            biome_noise: Noise::new(&mut rng),
+            cave_noise: Noise::new(&mut rng),
+            materials,
+            base_height: 0,
+            height_amplitude: 12.0,
+            height_scale: 48.0,
+            biome_scale: 96.0,
+            snow_height: 7,
+            cave_scale: 12.0,
+            cave_threshold: 0.3,
+            cave_depth: 3,
+        }
+    }
+
+    pub fn seed(&self) -> u64 {
+        self.seed
+    }
+
+    /// The Y coordinate of the topmost solid voxel of the column, ignoring caves
+    pub fn height(&self, x: i32, z: i32, w: i32) -> i32 {
+        let position = [x, z, w].map(|x| x as f32 / self.height_scale);
+        self.base_height
+            + (self.height_noise.fractal(position, 4) * self.height_amplitude).floor() as i32
+    }
+
+    pub fn biome(&self, x: i32, z: i32, w: i32) -> Biome {
+        let position = [x, z, w].map(|x| x as f32 / self.biome_scale);
+        if self.biome_noise.fractal(position, 2) < -0.15 {
+            Biome::Desert
+        } else if self.height(x, z, w) >= self.snow_height {
+            Biome::Mountains
+        } else {
+            Biome::Grassland
+        }
+    }
+
+    fn is_cave(&self, position: cgmath::Vector4<i32>) -> bool {
+        let position =
+            [position.x, position.y, position.z, position.w].map(|x| x as f32 / self.cave_scale);
+        self.cave_noise.fractal(position, 3) > self.cave_threshold
+    }
+
+    /// The material of the voxel `depth` voxels below the surface of a column, where 0 is the surface itself
+    fn layer(&self, biome: Biome, depth: i32) -> u32 {
+        match (biome, depth) {
+            (Biome::Grassland, 0) => self.materials.grass,
+            (Biome::Grassland, 1..=3) => self.materials.dirt,
+            (Biome::Desert, 0..=3) => self.materials.sand,
+            (Biome::Mountains, 0) => self.materials.snow,
+            _ => self.materials.stone,
+        }
+    }
+
+    pub fn voxel(&self, position: cgmath::Vector4<i32>) -> Voxel {
+        let height = self.height(position.x, position.z, position.w);
+        if position.y > height {
+            return Voxel::EMPTY;
+        }
+        let depth = height - position.y;
+        if depth >= self.cave_depth && self.is_cave(position) {
+            return Voxel::EMPTY;
+        }
+        Voxel::new(self.layer(self.biome(position.x, position.z, position.w), depth))
+    }
+
+    /// Generates every voxel of a chunk, the same as calling `voxel` for each of them
+    pub fn generate_chunk(&self, chunk_position: cgmath::Vector4<i32>) -> Chunk {
+        let size = CHUNK_SIZE as i32;
+        let origin = chunk_position * size;
+        let mut chunk = Chunk::new();
+        for w in 0..size {
+            for z in 0..size {
+                for x in 0..size {
+                    let height = self.height(origin.x + x, origin.z + z, origin.w + w);
+                    if origin.y > height {
+                        continue;
+                    }
+                    let biome = self.biome(origin.x + x, origin.z + z, origin.w + w);
+                    for y in 0..size {
+                        let position = origin + cgmath::vec4(x, y, z, w);
+                        let depth = height - position.y;
+                        if depth < 0 || (depth >= self.cave_depth && self.is_cave(position)) {
+                            continue;
+                        }
+                        chunk.data[Chunk::index(cgmath::vec4(x, y, z, w).cast().unwrap())] =
+                            Voxel::new(self.layer(biome, depth));
+                    }
+                }
+            }
+        }
+        chunk
+    }
+
+    /// Generates the chunks from `min` to `max` inclusive into `world`,
+    /// replacing whatever was there and leaving out chunks that are entirely empty
+    pub fn fill(&self, world: &mut World, min: cgmath::Vector4<i32>, max: cgmath::Vector4<i32>) {
+        for w in min.w..=max.w {
+            for z in min.z..=max.z {
+                for y in min.y..=max.y {
+                    for x in min.x..=max.x {
+                        let chunk_position = cgmath::vec4(x, y, z, w);
+                        let chunk = self.generate_chunk(chunk_position);
+                        if chunk.is_empty() {
+                            world.remove_chunk(chunk_position);
+                        } else {
+                            world.insert_chunk(chunk_position, chunk);
+                        }
+                    }
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,190 @@
+use std::collections::VecDeque;
+
+use crate::{Chunk, Voxel, World, CHUNK_SIZE, CHUNK_VOLUME};
+
+/// The light level of the brightest emissive voxels, which goes down by one with each step away from them
+pub const MAX_LIGHT_LEVEL: u32 = 15;
+
+/// One step along each axis either way, which is how far light spreads each level
+const NEIGHBOURS: [cgmath::Vector4<i32>; 8] = [
+    cgmath::vec4(1, 0, 0, 0),
+    cgmath::vec4(-1, 0, 0, 0),
+    cgmath::vec4(0, 1, 0, 0),
+    cgmath::vec4(0, -1, 0, 0),
+    cgmath::vec4(0, 0, 1, 0),
+    cgmath::vec4(0, 0, -1, 0),
+    cgmath::vec4(0, 0, 0, 1),
+    cgmath::vec4(0, 0, 0, -1),
+];
+
+fn voxel_position(chunk_position: cgmath::Vector4<i32>, index: usize) -> cgmath::Vector4<i32> {
+    let size = CHUNK_SIZE as i32;
+    let index = index as i32;
+    chunk_position * size
+        + cgmath::vec4(
+            index % size,
+            index / size % size,
+            index / (size * size) % size,
+            index / (size * size * size),
+        )
+}
+
+/// A flood fill of `Voxel::light` out from emissive voxels through the empty voxels around them,
+/// limited to the voxels that changed
+///
+/// Light that went out is taken away first, as far as it had spread, and then whatever is still lit around it
+/// spreads back in. Light is only stored in chunks that exist, so it stops where they end.
+#[derive(Debug, Default)]
+pub struct LightUpdate {
+    /// Voxels whose light went out, with the level they had
+    darkened: VecDeque<(cgmath::Vector4<i32>, u32)>,
+    /// Voxels whose light has to spread to their neighbours
+    lit: VecDeque<cgmath::Vector4<i32>>,
+}
+
+impl LightUpdate {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Queues up the changes around the voxel at `position`, which was `old` and has just been set with no light
+    pub fn voxel_changed(&mut self, world: &mut World, position: cgmath::Vector4<i32>, old: Voxel) {
+        if old.light > 0 {
+            self.darkened.push_back((position, old.light));
+        }
+        let voxel = world.get_voxel(position);
+        let emitted = world.emitted_light(voxel);
+        if emitted > 0 {
+            world.set_light(position, emitted);
+            self.lit.push_back(position);
+        }
+        // light can now get through from all sides
+        if voxel.is_empty() {
+            for offset in NEIGHBOURS {
+                if world.get_voxel(position + offset).light > 0 {
+                    self.lit.push_back(position + offset);
+                }
+            }
+        }
+    }
+
+    /// Queues up the light of the chunk that has just been inserted with no light, replacing `old`,
+    /// along with the light of the chunks next to it
+    pub fn chunk_inserted(
+        &mut self,
+        world: &mut World,
+        chunk_position: cgmath::Vector4<i32>,
+        old: Option<&Chunk>,
+    ) {
+        if let Some(old) = old {
+            self.chunk_removed(chunk_position, old);
+        }
+
+        let emitters = world
+            .chunk(chunk_position)
+            .unwrap()
+            .data
+            .iter()
+            .enumerate()
+            .map(|(index, &voxel)| (index, world.emitted_light(voxel)))
+            .filter(|&(_, emitted)| emitted > 0)
+            .collect::<Vec<_>>();
+        for (index, emitted) in emitters {
+            let position = voxel_position(chunk_position, index);
+            world.set_light(position, emitted);
+            self.lit.push_back(position);
+        }
+
+        // the voxels of neighbouring chunks on the side facing this one
+        for (axis, offset) in NEIGHBOURS.into_iter().enumerate().map(|(i, o)| (i / 2, o)) {
+            let Some(neighbour) = world.chunk(chunk_position + offset) else {
+                continue;
+            };
+            let facing = if offset[axis] > 0 {
+                0
+            } else {
+                CHUNK_SIZE as i32 - 1
+            };
+            for index in 0..CHUNK_VOLUME {
+                let position = voxel_position(chunk_position + offset, index);
+                if position[axis].rem_euclid(CHUNK_SIZE as i32) == facing
+                    && neighbour.data[index].light > 0
+                {
+                    self.lit.push_back(position);
+                }
+            }
+        }
+    }
+
+    /// Queues up taking away the light of a chunk that has just been removed
+    pub fn chunk_removed(&mut self, chunk_position: cgmath::Vector4<i32>, chunk: &Chunk) {
+        for (index, voxel) in chunk.data.iter().enumerate() {
+            if voxel.light > 0 {
+                self.darkened
+                    .push_back((voxel_position(chunk_position, index), voxel.light));
+            }
+        }
+    }
+
+    /// Queues up the changes around every voxel of the material `id`, whose light level has just changed from `old_level`
+    pub fn material_changed(&mut self, world: &mut World, id: u32, old_level: u32) {
+        let positions = world
+            .chunks()
+            .flat_map(|(chunk_position, chunk)| {
+                chunk
+                    .data
+                    .iter()
+                    .enumerate()
+                    .filter(|(_, voxel)| voxel.material == id)
+                    .map(move |(index, _)| voxel_position(chunk_position, index))
+            })
+            .collect::<Vec<_>>();
+        let level = world.materials()[id as usize].light_level();
+        for position in positions {
+            if old_level > 0 {
+                self.darkened.push_back((position, old_level));
+            }
+            world.set_light(position, level);
+            if level > 0 {
+                self.lit.push_back(position);
+            }
+        }
+    }
+
+    /// Takes away the light that went out, and then spreads the light that is left
+    pub fn run(mut self, world: &mut World) {
+        while let Some((position, level)) = self.darkened.pop_front() {
+            for offset in NEIGHBOURS {
+                let neighbour = position + offset;
+                let voxel = world.get_voxel(neighbour);
+                if voxel.light == 0 {
+                    continue;
+                }
+                if voxel.is_empty() && voxel.light < level {
+                    world.set_light(neighbour, 0);
+                    self.darkened.push_back((neighbour, voxel.light));
+                } else {
+                    // lit by something else, or an emissive voxel itself
+                    self.lit.push_back(neighbour);
+                }
+            }
+        }
+
+        while let Some(position) = self.lit.pop_front() {
+            let level = world.get_voxel(position).light;
+            if level <= 1 {
+                continue;
+            }
+            for offset in NEIGHBOURS {
+                let neighbour = position + offset;
+                let voxel = world.get_voxel(neighbour);
+                if voxel.is_empty()
+                    && voxel.light + 1 < level
+                    && world.set_light(neighbour, level - 1)
+                {
+                    self.lit.push_back(neighbour);
+                }
+            }
+        }
+    }
+}
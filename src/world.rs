@@ -0,0 +1,327 @@
+use encase::{ArrayLength, ShaderType};
+use std::collections::HashMap;
+use std::ops::Range;
+
+use crate::{ChunkOccupancy, LightUpdate, CHUNK_SIZE, MAX_LIGHT_LEVEL};
+
+pub const CHUNK_VOLUME: usize = (CHUNK_SIZE * CHUNK_SIZE * CHUNK_SIZE * CHUNK_SIZE) as usize;
+
+#[derive(Clone, Copy, PartialEq, Debug, ShaderType)]
+pub struct Material {
+    pub color: cgmath::Vector3<f32>,
+    /// The fraction of the light that is mirrored off the surface
+    pub reflectivity: f32,
+    /// The fraction of the light that passes through, tinted by `color`
+    pub transparency: f32,
+    /// How much light bends going into the material from empty space, which has an index of 1
+    pub refractive_index: f32,
+    /// How much light the material gives off in its own color, on top of any it's lit by
+    pub emission: f32,
+}
+
+impl Material {
+    /// An opaque material that doesn't reflect or give off light
+    pub fn new(color: cgmath::Vector3<f32>) -> Self {
+        Self {
+            color,
+            reflectivity: 0.0,
+            transparency: 0.0,
+            refractive_index: 1.5,
+            emission: 0.0,
+        }
+    }
+
+    /// The fraction of the light that the surface is lit by, rather than reflected or let through
+    pub fn opacity(&self) -> f32 {
+        (1.0 - self.reflectivity - self.transparency).max(0.0)
+    }
+
+    /// The light level that voxels of the material give off, which is `MAX_LIGHT_LEVEL` from an emission of 1
+    pub fn light_level(&self) -> u32 {
+        (self.emission.clamp(0.0, 1.0) * MAX_LIGHT_LEVEL as f32).round() as u32
+    }
+}
+
+#[derive(ShaderType)]
+pub struct Materials<'a> {
+    pub count: ArrayLength,
+    #[size(runtime)]
+    pub data: &'a [Material],
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug, ShaderType)]
+pub struct Voxel {
+    pub material: u32,
+    /// From 0 to `MAX_LIGHT_LEVEL`, which the world works out from the emissive voxels around it
+    /// and ignores when setting voxels
+    pub light: u32,
+}
+
+impl Voxel {
+    pub const EMPTY: Self = Self {
+        material: u32::MAX,
+        light: 0,
+    };
+
+    /// An unlit voxel of `material`
+    pub fn new(material: u32) -> Self {
+        Self { material, light: 0 }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.material == u32::MAX
+    }
+}
+
+#[derive(Clone, ShaderType)]
+pub struct Chunk {
+    pub data: [Voxel; CHUNK_VOLUME],
+}
+
+impl Chunk {
+    pub fn new() -> Self {
+        Self {
+            data: [Voxel::EMPTY; CHUNK_VOLUME],
+        }
+    }
+
+    /// Same layout as `get_block_index` in the shader
+    pub fn index(local_position: cgmath::Vector4<u32>) -> usize {
+        (local_position.x
+            + local_position.y * CHUNK_SIZE
+            + local_position.z * CHUNK_SIZE * CHUNK_SIZE
+            + local_position.w * CHUNK_SIZE * CHUNK_SIZE * CHUNK_SIZE) as usize
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.data.iter().all(Voxel::is_empty)
+    }
+}
+
+impl Default for Chunk {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Which chunks have changed since the last call to `World::take_chunk_changes`
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum ChunkChanges {
+    /// Every chunk has to be uploaded again, because the world is new
+    All,
+    /// The chunks that have been changed, inserted or removed,
+    /// along with the range of `Chunk::data` that changed
+    Chunks(HashMap<cgmath::Vector4<i32>, Range<usize>>),
+}
+
+pub struct World {
+    chunks: HashMap<cgmath::Vector4<i32>, Box<Chunk>>,
+    materials: Vec<Material>,
+    all_chunks_dirty: bool,
+    dirty_chunks: HashMap<cgmath::Vector4<i32>, Range<usize>>,
+    materials_dirty: bool,
+    occupancy: ChunkOccupancy,
+}
+
+impl World {
+    pub fn new() -> Self {
+        Self {
+            chunks: HashMap::new(),
+            materials: Vec::new(),
+            all_chunks_dirty: true,
+            dirty_chunks: HashMap::new(),
+            materials_dirty: true,
+            occupancy: ChunkOccupancy::new(),
+        }
+    }
+
+    /// The red, green and blue voxels that the app starts with
+    pub fn example() -> Self {
+        let mut world = Self::new();
+        let red = world.add_material(Material::new(cgmath::vec3(1.0, 0.0, 0.0)));
+        let green = world.add_material(Material::new(cgmath::vec3(0.0, 1.0, 0.0)));
+        let blue = world.add_material(Material::new(cgmath::vec3(0.0, 0.0, 1.0)));
+        world.set_voxel(cgmath::vec4(0, 0, 0, 0), Voxel::new(red));
+        world.set_voxel(cgmath::vec4(2, 0, 0, 0), Voxel::new(green));
+        world.set_voxel(cgmath::vec4(0, 1, 0, 0), Voxel::new(blue));
+        world
+    }
+
+    pub fn materials(&self) -> &[Material] {
+        &self.materials
+    }
+
+    /// Returns the id to use for `Voxel::material`
+    pub fn add_material(&mut self, material: Material) -> u32 {
+        self.materials_dirty = true;
+        self.materials.push(material);
+        let id = (self.materials.len() - 1) as u32;
+        // voxels can refer to materials before they're added
+        self.relight_material(id, 0);
+        id
+    }
+
+    pub fn set_material(&mut self, id: u32, material: Material) {
+        let old = &mut self.materials[id as usize];
+        if *old != material {
+            let old_level = old.light_level();
+            *old = material;
+            self.materials_dirty = true;
+            self.relight_material(id, old_level);
+        }
+    }
+
+    fn relight_material(&mut self, id: u32, old_level: u32) {
+        if self.materials[id as usize].light_level() != old_level {
+            let mut update = LightUpdate::new();
+            update.material_changed(self, id, old_level);
+            update.run(self);
+        }
+    }
+
+    /// The light level that `voxel` gives off, which is 0 for empty voxels and materials that haven't been added
+    pub fn emitted_light(&self, voxel: Voxel) -> u32 {
+        self.materials
+            .get(voxel.material as usize)
+            .map_or(0, Material::light_level)
+    }
+
+    /// Returns whether the materials have changed since the last call, and resets the flag
+    pub fn take_materials_dirty(&mut self) -> bool {
+        std::mem::take(&mut self.materials_dirty)
+    }
+
+    fn mark_dirty(&mut self, chunk_position: cgmath::Vector4<i32>, range: Range<usize>) {
+        self.dirty_chunks
+            .entry(chunk_position)
+            .and_modify(|dirty| *dirty = dirty.start.min(range.start)..dirty.end.max(range.end))
+            .or_insert(range);
+    }
+
+    /// Returns which chunks have changed since the last call, and resets them
+    pub fn take_chunk_changes(&mut self) -> ChunkChanges {
+        let dirty_chunks = std::mem::take(&mut self.dirty_chunks);
+        if std::mem::take(&mut self.all_chunks_dirty) {
+            ChunkChanges::All
+        } else {
+            ChunkChanges::Chunks(dirty_chunks)
+        }
+    }
+
+    /// Splits a voxel position into the position of its chunk and the position within that chunk
+    pub fn split_position(
+        position: cgmath::Vector4<i32>,
+    ) -> (cgmath::Vector4<i32>, cgmath::Vector4<u32>) {
+        let size = CHUNK_SIZE as i32;
+        (
+            position.map(|x| x.div_euclid(size)),
+            position.map(|x| x.rem_euclid(size) as u32),
+        )
+    }
+
+    pub fn get_voxel(&self, position: cgmath::Vector4<i32>) -> Voxel {
+        let (chunk_position, local_position) = Self::split_position(position);
+        self.chunks
+            .get(&chunk_position)
+            .map_or(Voxel::EMPTY, |chunk| {
+                chunk.data[Chunk::index(local_position)]
+            })
+    }
+
+    /// Creates the containing chunk if it does not exist yet, and updates the light around the voxel
+    pub fn set_voxel(&mut self, position: cgmath::Vector4<i32>, voxel: Voxel) {
+        let (chunk_position, local_position) = Self::split_position(position);
+        if voxel.is_empty() && !self.chunks.contains_key(&chunk_position) {
+            return;
+        }
+        let index = Chunk::index(local_position);
+        let occupancy = &mut self.occupancy;
+        let mut created = false;
+        let old = &mut self
+            .chunks
+            .entry(chunk_position)
+            .or_insert_with(|| {
+                occupancy.insert(chunk_position);
+                created = true;
+                Box::default()
+            })
+            .data[index];
+        if old.material == voxel.material {
+            return;
+        }
+        let old_voxel = std::mem::replace(old, Voxel::new(voxel.material));
+        self.mark_dirty(chunk_position, index..index + 1);
+
+        let mut update = LightUpdate::new();
+        if created {
+            update.chunk_inserted(self, chunk_position, None);
+        }
+        update.voxel_changed(self, position, old_voxel);
+        update.run(self);
+    }
+
+    /// Sets the light level of a voxel without spreading it to the voxels around it,
+    /// returning false if there is no chunk to store it in
+    ///
+    /// The light is updated on its own as the world is edited, so this is only for `LightUpdate`
+    pub fn set_light(&mut self, position: cgmath::Vector4<i32>, light: u32) -> bool {
+        let (chunk_position, local_position) = Self::split_position(position);
+        let Some(chunk) = self.chunks.get_mut(&chunk_position) else {
+            return false;
+        };
+        let index = Chunk::index(local_position);
+        if chunk.data[index].light != light {
+            chunk.data[index].light = light;
+            self.mark_dirty(chunk_position, index..index + 1);
+        }
+        true
+    }
+
+    pub fn chunk(&self, chunk_position: cgmath::Vector4<i32>) -> Option<&Chunk> {
+        self.chunks.get(&chunk_position).map(|chunk| &**chunk)
+    }
+
+    /// Replaces the light levels in `chunk` with the ones from the world around it
+    pub fn insert_chunk(&mut self, chunk_position: cgmath::Vector4<i32>, mut chunk: Chunk) {
+        self.mark_dirty(chunk_position, 0..CHUNK_VOLUME);
+        for voxel in &mut chunk.data {
+            voxel.light = 0;
+        }
+        let old = self.chunks.insert(chunk_position, Box::new(chunk));
+        if old.is_none() {
+            self.occupancy.insert(chunk_position);
+        }
+
+        let mut update = LightUpdate::new();
+        update.chunk_inserted(self, chunk_position, old.as_deref());
+        update.run(self);
+    }
+
+    pub fn remove_chunk(&mut self, chunk_position: cgmath::Vector4<i32>) -> Option<Chunk> {
+        let chunk = self.chunks.remove(&chunk_position)?;
+        self.occupancy.remove(chunk_position);
+        self.mark_dirty(chunk_position, 0..CHUNK_VOLUME);
+
+        let mut update = LightUpdate::new();
+        update.chunk_removed(chunk_position, &chunk);
+        update.run(self);
+        Some(*chunk)
+    }
+
+    /// Which parts of space have chunks in them, at increasingly coarse levels
+    pub fn occupancy(&self) -> &ChunkOccupancy {
+        &self.occupancy
+    }
+
+    pub fn chunks(&self) -> impl Iterator<Item = (cgmath::Vector4<i32>, &Chunk)> {
+        self.chunks
+            .iter()
+            .map(|(&position, chunk)| (position, &**chunk))
+    }
+}
+
+impl Default for World {
+    fn default() -> Self {
+        Self::new()
+    }
+}
@@ -0,0 +1,280 @@
+//! The binary world file format
+//!
+//! All numbers are little endian, and the file is laid out as
+//! - the magic bytes `TSRW` and the version, a `u32`
+//! - the chunk size, the size of a voxel record and the size of a material record, all `u32`s
+//! - the number of materials, a `u32`, followed by that many material records
+//! - the number of chunks, a `u32`, followed by that many chunks
+//!
+//! Each chunk is its position as four `i32`s, then the length in bytes of its voxel data as a `u32`,
+//! then the voxel data run length encoded as pairs of a `u32` count and a voxel record, in `Chunk::index` order.
+//!
+//! Record sizes are stored so that fields added to the end of `Voxel` or `Material` later on
+//! can be given defaults when loading older files, and unknown fields from newer files can be skipped.
+//! Chunks saved with a different chunk size are split up voxel by voxel when loading.
+
+use std::io::{self, Read, Write};
+
+use crate::{Chunk, Material, Voxel, World, CHUNK_SIZE};
+
+pub const WORLD_FILE_MAGIC: [u8; 4] = *b"TSRW";
+pub const WORLD_FILE_VERSION: u32 = 1;
+
+const VOXEL_RECORD_SIZE: u32 = 4;
+const MATERIAL_RECORD_SIZE: u32 = 28;
+/// The material, which every version has saved
+const MIN_VOXEL_RECORD_SIZE: u32 = 4;
+/// The color, which every version has saved
+const MIN_MATERIAL_RECORD_SIZE: u32 = 12;
+/// Anything larger than these is treated as a corrupt file rather than allocated
+const MAX_CHUNK_SIZE: u32 = 16;
+const MAX_VOXEL_RECORD_SIZE: u32 = VOXEL_RECORD_SIZE * 4;
+const MAX_MATERIAL_RECORD_SIZE: u32 = MATERIAL_RECORD_SIZE * 4;
+const MAX_MATERIAL_COUNT: u32 = 1 << 16;
+const MAX_CHUNK_COUNT: u32 = 1 << 20;
+
+fn invalid_data(message: impl Into<String>) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, message.into())
+}
+
+fn write_u32(writer: &mut impl Write, value: u32) -> io::Result<()> {
+    writer.write_all(&value.to_le_bytes())
+}
+
+fn read_u32(reader: &mut impl Read) -> io::Result<u32> {
+    let mut bytes = [0; 4];
+    reader.read_exact(&mut bytes)?;
+    Ok(u32::from_le_bytes(bytes))
+}
+
+fn read_i32(reader: &mut impl Read) -> io::Result<i32> {
+    let mut bytes = [0; 4];
+    reader.read_exact(&mut bytes)?;
+    Ok(i32::from_le_bytes(bytes))
+}
+
+fn read_record(reader: &mut impl Read, size: u32) -> io::Result<Vec<u8>> {
+    let mut record = vec![0; size as usize];
+    reader.read_exact(&mut record)?;
+    Ok(record)
+}
+
+/// Reads the `index`th 4 byte field of a record, or `None` if the record was saved before the field existed
+fn record_field(record: &[u8], index: usize) -> Option<[u8; 4]> {
+    record.get(index * 4..index * 4 + 4)?.try_into().ok()
+}
+
+fn write_voxel(writer: &mut impl Write, voxel: Voxel) -> io::Result<()> {
+    write_u32(writer, voxel.material)
+}
+
+fn read_voxel(record: &[u8]) -> Voxel {
+    Voxel::new(record_field(record, 0).map_or(Voxel::EMPTY.material, u32::from_le_bytes))
+}
+
+fn write_material(writer: &mut impl Write, material: Material) -> io::Result<()> {
+    for field in [
+        material.color.x,
+        material.color.y,
+        material.color.z,
+        material.reflectivity,
+        material.transparency,
+        material.refractive_index,
+        material.emission,
+    ] {
+        writer.write_all(&field.to_le_bytes())?;
+    }
+    Ok(())
+}
+
+/// Fields missing from older files get the defaults of `Material::new`
+fn read_material(record: &[u8]) -> Material {
+    let field = |index, default| record_field(record, index).map_or(default, f32::from_le_bytes);
+    let color = cgmath::vec3(field(0, 0.0), field(1, 0.0), field(2, 0.0));
+    let default = Material::new(color);
+    Material {
+        color,
+        reflectivity: field(3, default.reflectivity),
+        transparency: field(4, default.transparency),
+        refractive_index: field(5, default.refractive_index),
+        emission: field(6, default.emission),
+    }
+}
+
+fn encode_chunk(chunk: &Chunk) -> io::Result<Vec<u8>> {
+    let mut data = Vec::new();
+    // light isn't saved, since it's worked out again when loading
+    let mut voxels = chunk
+        .data
+        .iter()
+        .map(|voxel| Voxel::new(voxel.material))
+        .peekable();
+    while let Some(voxel) = voxels.next() {
+        let mut count = 1;
+        while voxels.next_if_eq(&voxel).is_some() {
+            count += 1;
+        }
+        write_u32(&mut data, count)?;
+        write_voxel(&mut data, voxel)?;
+    }
+    Ok(data)
+}
+
+fn decode_chunk(
+    mut data: &[u8],
+    volume: usize,
+    voxel_record_size: u32,
+    material_count: u32,
+) -> io::Result<Vec<Voxel>> {
+    // grown as the runs are read rather than sized up front, since `volume` comes from the file
+    let mut voxels = Vec::new();
+    while !data.is_empty() {
+        let count = read_u32(&mut data)? as usize;
+        let voxel = read_voxel(&read_record(&mut data, voxel_record_size)?);
+        if !voxel.is_empty() && voxel.material >= material_count {
+            return Err(invalid_data(format!(
+                "voxel has material {} but there are only {material_count} materials",
+                voxel.material
+            )));
+        }
+        if count > volume - voxels.len() {
+            return Err(invalid_data("chunk has more voxels than fit in it"));
+        }
+        voxels.extend(std::iter::repeat_n(voxel, count));
+    }
+    if voxels.len() != volume {
+        return Err(invalid_data("chunk has fewer voxels than fit in it"));
+    }
+    Ok(voxels)
+}
+
+/// The position of the first voxel of the chunk at `position`, or `None` if any of its voxels
+/// would be outside of the range of `i32`
+fn chunk_origin(position: cgmath::Vector4<i32>, size: i32) -> Option<cgmath::Vector4<i32>> {
+    let origin = |x: i32| {
+        x.checked_mul(size)
+            .filter(|origin| origin.checked_add(size - 1).is_some())
+    };
+    Some(cgmath::vec4(
+        origin(position.x)?,
+        origin(position.y)?,
+        origin(position.z)?,
+        origin(position.w)?,
+    ))
+}
+
+/// Writes `world` in the format described in the module documentation
+pub fn save_world(writer: &mut impl Write, world: &World) -> io::Result<()> {
+    writer.write_all(&WORLD_FILE_MAGIC)?;
+    write_u32(writer, WORLD_FILE_VERSION)?;
+    write_u32(writer, CHUNK_SIZE)?;
+    write_u32(writer, VOXEL_RECORD_SIZE)?;
+    write_u32(writer, MATERIAL_RECORD_SIZE)?;
+
+    write_u32(writer, world.materials().len() as u32)?;
+    for &material in world.materials() {
+        write_material(writer, material)?;
+    }
+
+    let chunks = world
+        .chunks()
+        .filter(|(_, chunk)| !chunk.is_empty())
+        .collect::<Vec<_>>();
+    write_u32(writer, chunks.len() as u32)?;
+    for (position, chunk) in chunks {
+        for component in [position.x, position.y, position.z, position.w] {
+            writer.write_all(&component.to_le_bytes())?;
+        }
+        let data = encode_chunk(chunk)?;
+        write_u32(writer, data.len() as u32)?;
+        writer.write_all(&data)?;
+    }
+    Ok(())
+}
+
+/// Reads a world written by `save_world`, including by older versions of it
+pub fn load_world(reader: &mut impl Read) -> io::Result<World> {
+    let mut magic = [0; 4];
+    reader.read_exact(&mut magic)?;
+    if magic != WORLD_FILE_MAGIC {
+        return Err(invalid_data("not a world file"));
+    }
+    let version = read_u32(reader)?;
+    if version > WORLD_FILE_VERSION {
+        return Err(invalid_data(format!(
+            "world file version {version} is newer than the supported version {WORLD_FILE_VERSION}"
+        )));
+    }
+
+    let chunk_size = read_u32(reader)?;
+    if chunk_size == 0 || chunk_size > MAX_CHUNK_SIZE {
+        return Err(invalid_data(format!("invalid chunk size {chunk_size}")));
+    }
+    let voxel_record_size = read_u32(reader)?;
+    let material_record_size = read_u32(reader)?;
+    if !(MIN_VOXEL_RECORD_SIZE..=MAX_VOXEL_RECORD_SIZE).contains(&voxel_record_size) {
+        return Err(invalid_data(format!(
+            "invalid voxel record size {voxel_record_size}"
+        )));
+    }
+    if !(MIN_MATERIAL_RECORD_SIZE..=MAX_MATERIAL_RECORD_SIZE).contains(&material_record_size) {
+        return Err(invalid_data(format!(
+            "invalid material record size {material_record_size}"
+        )));
+    }
+
+    let mut world = World::new();
+    let material_count = read_u32(reader)?;
+    if material_count > MAX_MATERIAL_COUNT {
+        return Err(invalid_data(format!(
+            "{material_count} materials is more than the most of {MAX_MATERIAL_COUNT}"
+        )));
+    }
+    for _ in 0..material_count {
+        world.add_material(read_material(&read_record(reader, material_record_size)?));
+    }
+
+    let volume = chunk_size.pow(4) as usize;
+    let chunk_count = read_u32(reader)?;
+    if chunk_count > MAX_CHUNK_COUNT {
+        return Err(invalid_data(format!(
+            "{chunk_count} chunks is more than the most of {MAX_CHUNK_COUNT}"
+        )));
+    }
+    for _ in 0..chunk_count {
+        let position = cgmath::vec4(
+            read_i32(reader)?,
+            read_i32(reader)?,
+            read_i32(reader)?,
+            read_i32(reader)?,
+        );
+        let length = read_u32(reader)?;
+        let mut data = Vec::new();
+        reader.take(length as u64).read_to_end(&mut data)?;
+        if data.len() != length as usize {
+            return Err(io::ErrorKind::UnexpectedEof.into());
+        }
+        let voxels = decode_chunk(&data, volume, voxel_record_size, material_count)?;
+        let size = chunk_size as i32;
+        let origin = chunk_origin(position, size)
+            .ok_or_else(|| invalid_data(format!("chunk position {position:?} is out of range")))?;
+
+        if chunk_size == CHUNK_SIZE {
+            let mut chunk = Chunk::new();
+            chunk.data.copy_from_slice(&voxels);
+            world.insert_chunk(position, chunk);
+        } else {
+            for (index, &voxel) in voxels.iter().enumerate() {
+                let index = index as i32;
+                let local = cgmath::vec4(
+                    index % size,
+                    index / size % size,
+                    index / (size * size) % size,
+                    index / (size * size * size),
+                );
+                world.set_voxel(origin + local, voxel);
+            }
+        }
+    }
+    Ok(world)
+}
@@ -0,0 +1,31 @@
+use tesseracts::{Camera, RotationPlane};
+
+#[test]
+fn orthonormalize_is_stable() {
+    let mut camera = Camera::default();
+    for (i, plane) in [
+        RotationPlane::XY,
+        RotationPlane::XZ,
+        RotationPlane::XW,
+        RotationPlane::YZ,
+        RotationPlane::YW,
+        RotationPlane::ZW,
+    ]
+    .into_iter()
+    .cycle()
+    .take(600)
+    .enumerate()
+    {
+        camera.rotate(plane, 0.1 + i as f32 * 0.037);
+    }
+    // the rotations have left the axes a little off, which the first call fixes
+    camera.orientation.x *= 1.001;
+    camera.orthonormalize();
+    let gpu = camera.gpu();
+
+    // and any calls after that, as happens every frame, leave the camera exactly as it was
+    camera.orthonormalize();
+    assert_eq!(camera.gpu(), gpu);
+    camera.orthonormalize();
+    assert_eq!(camera.gpu(), gpu);
+}
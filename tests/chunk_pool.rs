@@ -0,0 +1,125 @@
+mod common;
+
+use common::{create_device, read_buffer};
+use tesseracts::{Chunk, ChunkPool, Voxel, CHUNK_SLOT_SIZE, CHUNK_VOLUME};
+
+/// A chunk filled with a material that identifies it
+fn chunk(id: u32) -> Chunk {
+    let mut chunk = Chunk::new();
+    chunk.data.fill(Voxel::new(id));
+    chunk
+}
+
+fn position(id: u32) -> cgmath::Vector4<i32> {
+    cgmath::vec4(id as i32, 0, -(id as i32), 1)
+}
+
+/// Checks that every chunk in the pool has its own slot holding its voxels
+fn check_contents(device: &eframe::wgpu::Device, queue: &eframe::wgpu::Queue, pool: &ChunkPool) {
+    let data = read_buffer(device, queue, pool.buffer());
+    let mut seen = std::collections::HashSet::new();
+    for (chunk_position, slot) in pool.slots() {
+        assert!(seen.insert(slot), "slot {slot} is used twice");
+        assert!(slot < pool.capacity());
+        let start = (slot as u64 * CHUNK_SLOT_SIZE) as usize;
+        // each voxel is its material followed by its light
+        let materials = data[start..start + CHUNK_VOLUME * 8]
+            .chunks(8)
+            .map(|bytes| u32::from_le_bytes(bytes[..4].try_into().unwrap()))
+            .collect::<Vec<_>>();
+        let id = chunk_position.x as u32;
+        assert!(materials.iter().all(|&material| material == id));
+    }
+}
+
+#[test]
+fn free_slots_are_reused_lowest_first() {
+    let Some((device, _)) = create_device() else {
+        eprintln!("skipping, no wgpu adapter is available");
+        return;
+    };
+
+    let mut pool = ChunkPool::new(&device);
+    for id in 0..4 {
+        assert_eq!(pool.allocate(position(id)), id);
+    }
+    assert_eq!(pool.free(position(2)), Some(2));
+    assert_eq!(pool.free(position(0)), Some(0));
+    assert_eq!(pool.free(position(0)), None);
+    assert_eq!(pool.allocate(position(4)), 0);
+    assert_eq!(pool.allocate(position(5)), 2);
+    assert_eq!(pool.allocate(position(6)), 4);
+
+    // free slots at the end don't count as used
+    pool.free(position(6));
+    pool.free(position(3));
+    assert_eq!(pool.used_slots(), 3);
+    assert_eq!(pool.allocate(position(7)), 3);
+}
+
+#[test]
+fn growing_and_compacting_keep_contents() {
+    let Some((device, queue)) = create_device() else {
+        eprintln!("skipping, no wgpu adapter is available");
+        return;
+    };
+
+    let mut pool = ChunkPool::new(&device);
+    let mut reallocations = 0;
+    for id in 0..300 {
+        pool.allocate(position(id));
+        reallocations += pool.reserve(&device, &queue) as u32;
+        pool.write(&queue, position(id), 0..CHUNK_VOLUME, &chunk(id));
+    }
+    // the capacity doubles rather than growing one slot at a time
+    assert_eq!(pool.capacity(), 512);
+    assert_eq!(reallocations, 9);
+    check_contents(&device, &queue, &pool);
+
+    // partial writes only touch their range
+    let mut edited = chunk(7);
+    edited.data[10] = Voxel::new(1000);
+    pool.write(&queue, position(7), 10..11, &edited);
+    let data = read_buffer(&device, &queue, pool.buffer());
+    let start = (pool.slot(position(7)).unwrap() as u64 * CHUNK_SLOT_SIZE) as usize;
+    assert_eq!(
+        data[start + 72..start + 96],
+        [7, 0, 0, 0, 0, 0, 0, 0, 0xE8, 3, 0, 0, 0, 0, 0, 0, 7, 0, 0, 0, 0, 0, 0, 0]
+    );
+    pool.write(&queue, position(7), 10..11, &chunk(7));
+
+    // keep every tenth chunk
+    for id in 0..300 {
+        if id % 10 != 0 {
+            pool.free(position(id));
+        }
+    }
+    assert_eq!(pool.len(), 30);
+    assert_eq!(pool.used_slots(), 291);
+    assert!(pool.needs_compaction());
+
+    let compaction = pool.compact(&device, &queue);
+    assert!(!pool.needs_compaction());
+    assert_eq!(pool.used_slots(), 30);
+    // the chunks that were already within the first 30 slots stay where they are
+    assert_eq!(compaction.moved.len(), 27);
+    for chunk_position in compaction.moved {
+        assert!(pool.slot(chunk_position).unwrap() < 30);
+    }
+    assert!(compaction.reallocated);
+    assert_eq!(pool.capacity(), 64);
+    check_contents(&device, &queue, &pool);
+
+    // freeing everything and compacting shrinks down to a single slot
+    let positions = pool
+        .slots()
+        .map(|(position, _)| position)
+        .collect::<Vec<_>>();
+    for chunk_position in positions {
+        pool.free(chunk_position);
+    }
+    assert!(pool.is_empty());
+    assert_eq!(pool.used_slots(), 0);
+    assert!(pool.compact(&device, &queue).reallocated);
+    assert_eq!(pool.capacity(), 1);
+}
@@ -0,0 +1,173 @@
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+
+use tesseracts::{Chunk, ChunkSource, ChunkStreamer, Voxel, World, CHUNK_SIZE};
+
+/// Fills the chunks with a W of 0 and leaves the rest empty
+struct Slab;
+
+impl ChunkSource for Slab {
+    fn load_chunk(&self, chunk_position: cgmath::Vector4<i32>) -> Chunk {
+        let mut chunk = Chunk::new();
+        if chunk_position.w == 0 {
+            chunk.data.fill(Voxel::new(0));
+        }
+        chunk
+    }
+}
+
+/// The same as `Slab`, but doesn't hand out any chunks until it's opened
+struct GatedSlab(Arc<AtomicBool>);
+
+impl ChunkSource for GatedSlab {
+    fn load_chunk(&self, chunk_position: cgmath::Vector4<i32>) -> Chunk {
+        while !self.0.load(Ordering::Acquire) {
+            std::thread::sleep(std::time::Duration::from_millis(1));
+        }
+        Slab.load_chunk(chunk_position)
+    }
+}
+
+struct Panicking;
+
+impl ChunkSource for Panicking {
+    fn load_chunk(&self, _: cgmath::Vector4<i32>) -> Chunk {
+        panic!("failed to load a chunk");
+    }
+}
+
+fn distance_squared(a: cgmath::Vector4<i32>, b: cgmath::Vector4<i32>) -> i32 {
+    let d = a - b;
+    d.x * d.x + d.y * d.y + d.z * d.z + d.w * d.w
+}
+
+/// Updates the streamer until everything it asked for has arrived
+fn settle(streamer: &mut ChunkStreamer, world: &mut World, camera_position: cgmath::Vector4<f32>) {
+    let start = std::time::Instant::now();
+    loop {
+        streamer.update(world, camera_position);
+        if streamer.pending_count() == 0 {
+            return;
+        }
+        assert!(
+            start.elapsed() < std::time::Duration::from_secs(30),
+            "chunks never arrived"
+        );
+        std::thread::sleep(std::time::Duration::from_millis(1));
+    }
+}
+
+#[test]
+fn loads_within_radius_and_unloads_beyond() {
+    let mut world = World::new();
+    let mut streamer = ChunkStreamer::new(Slab, 2.0);
+
+    settle(&mut streamer, &mut world, cgmath::vec4(0.5, 0.5, 0.5, 0.5));
+    let chunks = world
+        .chunks()
+        .map(|(position, _)| position)
+        .collect::<Vec<_>>();
+    // the chunks within a 3D ball of radius 2 at W = 0
+    assert_eq!(chunks.len(), 33);
+    for position in &chunks {
+        assert_eq!(position.w, 0);
+        assert!(distance_squared(*position, cgmath::vec4(0, 0, 0, 0)) <= 4);
+    }
+
+    // move 6 chunks along X, far enough that everything from before is out of range, even with the margin
+    let size = CHUNK_SIZE as f32;
+    settle(
+        &mut streamer,
+        &mut world,
+        cgmath::vec4(6.0 * size + 0.5, 0.5, 0.5, 0.5),
+    );
+    assert_eq!(world.chunks().count(), 33);
+    for (position, _) in world.chunks() {
+        assert!(distance_squared(position, cgmath::vec4(6, 0, 0, 0)) <= 4);
+    }
+}
+
+#[test]
+fn keeps_chunks_just_beyond_radius() {
+    let mut world = World::new();
+    let mut streamer = ChunkStreamer::new(Slab, 2.0);
+    settle(&mut streamer, &mut world, cgmath::vec4(0.5, 0.5, 0.5, 0.5));
+
+    // one chunk over, so the chunks at X = -2 are at a distance of 3, within the margin of 1
+    let size = CHUNK_SIZE as f32;
+    settle(
+        &mut streamer,
+        &mut world,
+        cgmath::vec4(size + 0.5, 0.5, 0.5, 0.5),
+    );
+    assert!(world.chunk(cgmath::vec4(-1, 0, 0, 0)).is_some());
+    assert!(world.chunk(cgmath::vec4(3, 0, 0, 0)).is_some());
+
+    // edits survive for as long as the chunk stays loaded
+    world.set_voxel(cgmath::vec4(-1, 0, 0, 0), Voxel::EMPTY);
+    settle(&mut streamer, &mut world, cgmath::vec4(0.5, 0.5, 0.5, 0.5));
+    assert!(world.get_voxel(cgmath::vec4(-1, 0, 0, 0)).is_empty());
+}
+
+#[test]
+fn radius_changes() {
+    let mut world = World::new();
+    let mut streamer = ChunkStreamer::new(Slab, 1.0);
+    settle(&mut streamer, &mut world, cgmath::vec4(0.5, 0.5, 0.5, 0.5));
+    assert_eq!(world.chunks().count(), 7);
+
+    streamer.radius = 2.0;
+    settle(&mut streamer, &mut world, cgmath::vec4(0.5, 0.5, 0.5, 0.5));
+    assert_eq!(world.chunks().count(), 33);
+
+    streamer.radius = 0.0;
+    settle(&mut streamer, &mut world, cgmath::vec4(0.5, 0.5, 0.5, 0.5));
+    // everything within the margin of 1 stays
+    assert_eq!(world.chunks().count(), 7);
+}
+
+#[test]
+fn dropping_with_pending_chunks() {
+    let mut world = World::new();
+    let mut streamer = ChunkStreamer::new(Slab, 6.0);
+    // the thread skips the rest of the queue rather than loading all of it before stopping
+    streamer.update(&mut world, cgmath::vec4(0.0, 0.0, 0.0, 0.0));
+    drop(streamer);
+}
+
+#[test]
+fn edits_while_loading_are_kept() {
+    let open = Arc::new(AtomicBool::new(false));
+    let mut world = World::new();
+    let mut streamer = ChunkStreamer::new(GatedSlab(open.clone()), 1.0);
+    let camera_position = cgmath::vec4(0.5, 0.5, 0.5, 0.5);
+    streamer.update(&mut world, camera_position);
+    assert!(streamer.pending_count() > 0);
+
+    world.set_voxel(cgmath::vec4(1, 1, 1, 1), Voxel::new(1));
+    open.store(true, Ordering::Release);
+    settle(&mut streamer, &mut world, camera_position);
+
+    // the edit is kept, and the rest of its chunk still loads around it
+    assert_eq!(world.get_voxel(cgmath::vec4(1, 1, 1, 1)), Voxel::new(1));
+    assert_eq!(world.get_voxel(cgmath::vec4(0, 0, 0, 0)), Voxel::new(0));
+    // the chunks that weren't edited still load
+    assert_eq!(world.get_voxel(cgmath::vec4(4, 0, 0, 0)), Voxel::new(0));
+}
+
+#[test]
+fn dropping_after_the_source_panicked() {
+    let mut world = World::new();
+    let mut streamer = ChunkStreamer::new(Panicking, 2.0);
+    streamer.update(&mut world, cgmath::vec4(0.0, 0.0, 0.0, 0.0));
+    std::thread::sleep(std::time::Duration::from_millis(50));
+
+    // moving requests more chunks from the thread, which has stopped by now
+    let size = CHUNK_SIZE as f32;
+    for x in 1..4 {
+        streamer.update(&mut world, cgmath::vec4(x as f32 * size, 0.0, 0.0, 0.0));
+    }
+    assert_eq!(streamer.pending_count(), 0);
+    assert_eq!(world.chunks().count(), 0);
+    drop(streamer);
+}
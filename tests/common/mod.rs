@@ -0,0 +1,47 @@
+use eframe::wgpu;
+
+/// A device on whichever adapter is available, which can be a software one,
+/// or `None` if there is no adapter at all so that the test can be skipped
+#[allow(dead_code)] // not every test uses it
+pub fn create_device() -> Option<(wgpu::Device, wgpu::Queue)> {
+    let instance = wgpu::Instance::new(wgpu::InstanceDescriptor {
+        backends: wgpu::util::backend_bits_from_env().unwrap_or(wgpu::Backends::all()),
+        ..Default::default()
+    });
+    let adapter = pollster::block_on(instance.request_adapter(&wgpu::RequestAdapterOptions {
+        power_preference: wgpu::PowerPreference::LowPower,
+        force_fallback_adapter: false,
+        compatible_surface: None,
+    }))?;
+    pollster::block_on(adapter.request_device(&Default::default(), None)).ok()
+}
+
+/// Copies the whole of `buffer` back from the GPU
+#[allow(dead_code)] // not every test uses it
+pub fn read_buffer(device: &wgpu::Device, queue: &wgpu::Queue, buffer: &wgpu::Buffer) -> Vec<u8> {
+    let size = buffer.size();
+    let staging = device.create_buffer(&wgpu::BufferDescriptor {
+        label: Some("Staging Buffer"),
+        size,
+        usage: wgpu::BufferUsages::COPY_DST | wgpu::BufferUsages::MAP_READ,
+        mapped_at_creation: false,
+    });
+    let mut encoder = device.create_command_encoder(&Default::default());
+    encoder.copy_buffer_to_buffer(buffer, 0, &staging, 0, size);
+    queue.submit([encoder.finish()]);
+
+    let slice = staging.slice(..);
+    slice.map_async(wgpu::MapMode::Read, |result| result.unwrap());
+    device.poll(wgpu::Maintain::Wait);
+    let data = slice.get_mapped_range().to_vec();
+    data
+}
+
+/// Checks that two colors are the same, give or take rounding
+#[allow(dead_code)] // not every test uses it
+pub fn assert_close(actual: cgmath::Vector3<f32>, expected: cgmath::Vector3<f32>) {
+    assert!(
+        (0..3).all(|i| (actual[i] - expected[i]).abs() < 1e-5),
+        "expected {expected:?}, got {actual:?}"
+    );
+}
@@ -0,0 +1,133 @@
+mod common;
+
+use common::assert_close;
+use tesseracts::{ray_trace, Camera, Environment, Lighting, Material, Ray, Voxel, World};
+
+const ZENITH: cgmath::Vector3<f32> = cgmath::vec3(0.0, 0.0, 1.0);
+const HORIZON: cgmath::Vector3<f32> = cgmath::vec3(1.0, 1.0, 1.0);
+const GROUND: cgmath::Vector3<f32> = cgmath::vec3(0.0, 1.0, 0.0);
+const SUN: cgmath::Vector3<f32> = cgmath::vec3(1.0, 1.0, 0.0);
+
+fn environment() -> Environment {
+    Environment {
+        zenith_color: ZENITH,
+        horizon_color: HORIZON,
+        ground_color: GROUND,
+        sun_disc_radius: cgmath::Deg(2.0),
+        sun_disc_color: SUN,
+        fog: false,
+        fog_density: 0.1,
+    }
+}
+
+#[test]
+fn sky_gradient() {
+    let environment = environment();
+    assert_close(
+        environment.sky_color(cgmath::vec4(0.0, 2.0, 0.0, 0.0)),
+        ZENITH,
+    );
+    assert_close(
+        environment.sky_color(cgmath::vec4(0.0, -0.5, 0.0, 0.0)),
+        GROUND,
+    );
+    // the horizon goes all the way around in X, Z and W
+    for direction in [
+        cgmath::vec4(1.0, 0.0, 0.0, 0.0),
+        cgmath::vec4(0.0, 0.0, -1.0, 0.0),
+        cgmath::vec4(0.0, 0.0, 1.0, 1.0),
+    ] {
+        assert_close(environment.sky_color(direction), HORIZON);
+    }
+    // more of the sky is close to the zenith color than the horizon color
+    let up = 0.5f32.sqrt().sqrt();
+    assert_close(
+        environment.sky_color(cgmath::vec4(0.0, 1.0, 0.0, 1.0)),
+        HORIZON * (1.0 - up) + ZENITH * up,
+    );
+    assert_close(
+        environment.sky_color(cgmath::vec4(1.0, -1.0, 0.0, 0.0)),
+        HORIZON * (1.0 - up) + GROUND * up,
+    );
+}
+
+#[test]
+fn sun_disc() {
+    let mut environment = environment();
+    let lighting = Lighting {
+        sun_direction: cgmath::vec4(0.0, 0.0, 0.0, 3.0),
+        ..Lighting::default()
+    };
+    let towards = |degrees: f32| {
+        let (sin, cos) = degrees.to_radians().sin_cos();
+        cgmath::vec4(0.0, sin, 0.0, cos)
+    };
+    assert_close(environment.background(&lighting, towards(0.0)), SUN);
+    assert_close(environment.background(&lighting, towards(1.9)), SUN);
+    assert_close(
+        environment.background(&lighting, towards(2.1)),
+        environment.sky_color(towards(2.1)),
+    );
+
+    environment.sun_disc_radius = cgmath::Deg(0.0);
+    assert_close(environment.background(&lighting, towards(0.0)), HORIZON);
+
+    // no sun direction, no sun
+    environment.sun_disc_radius = cgmath::Deg(2.0);
+    let lighting = Lighting {
+        sun_direction: cgmath::vec4(0.0, 0.0, 0.0, 0.0),
+        ..Lighting::default()
+    };
+    assert_close(environment.background(&lighting, towards(0.0)), HORIZON);
+}
+
+#[test]
+fn fog_falls_off_exponentially() {
+    let mut environment = environment();
+    assert_eq!(environment.fog(100.0), 0.0);
+    environment.fog = true;
+    assert_eq!(environment.fog(0.0), 0.0);
+    assert!((environment.fog(10.0) - (1.0 - (-1.0f32).exp())).abs() < 1e-6);
+    assert!(environment.fog(1000.0) > 0.999);
+}
+
+#[test]
+fn rays_see_the_environment() {
+    let mut world = World::new();
+    let white = world.add_material(Material::new(cgmath::vec3(1.0, 1.0, 1.0)));
+    world.set_voxel(cgmath::vec4(0, 0, 0, 0), Voxel::new(white));
+    // every face is white, whichever way it points
+    let lighting = Lighting {
+        sun_direction: cgmath::vec4(0.0, 1.0, 0.0, 0.0),
+        sun_intensity: 0.0,
+        ambient: 1.0,
+        shadows: false,
+        ambient_occlusion: false,
+        ..Lighting::default()
+    };
+    let mut environment = environment();
+    let trace = |environment: &Environment, direction| {
+        let ray = Ray {
+            origin: cgmath::vec4(0.5, 0.5, -3.5, 0.5),
+            direction,
+        };
+        ray_trace(&world, &Camera::default(), &lighting, environment, ray)
+    };
+
+    assert_close(trace(&environment, cgmath::vec4(0.0, 1.0, 0.0, 0.0)), SUN);
+    assert_close(
+        trace(&environment, cgmath::vec4(0.0, 0.0, -1.0, 0.0)),
+        HORIZON,
+    );
+
+    // the voxel is 3.5 away, so the fog replaces some of it with the horizon behind it
+    let forward = cgmath::vec4(0.0, 0.0, 1.0, 0.0);
+    assert_close(trace(&environment, forward), cgmath::vec3(1.0, 1.0, 1.0));
+    environment.fog = true;
+    environment.horizon_color = GROUND;
+    let fog = 1.0 - (-0.35f32).exp();
+    assert_close(
+        trace(&environment, forward),
+        cgmath::vec3(1.0, 1.0, 1.0) * (1.0 - fog) + GROUND * fog,
+    );
+}
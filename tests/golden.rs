@@ -0,0 +1,372 @@
+//! Renders canned scenes with the compute shaders on an offscreen device,
+//! and compares them against the reference images in `tests/golden`
+//!
+//! Run with `UPDATE_GOLDEN=1` to overwrite the reference images with the current output.
+//! When no adapter is available at all (not even a software one) the tests are skipped.
+
+mod common;
+
+use common::create_device;
+use tesseracts::{
+    Camera, Environment, FovAxis, Lighting, Projection, ProjectionMode, RenderMode, Renderer,
+    RotationPlane, Scene, TerrainGenerator, TerrainMaterials, Voxel, World,
+};
+
+const WIDTH: u32 = 160;
+const HEIGHT: u32 = 120;
+
+/// How far apart a channel can be before the pixel counts as different
+const CHANNEL_TOLERANCE: u8 = 8;
+/// The fraction of pixels that are allowed to differ, to allow for precision differences between adapters
+const PIXEL_TOLERANCE: f64 = 0.01;
+/// How many frames `RenderMode::PathTraced` accumulates before the image is compared
+const PATH_TRACED_SAMPLES: u32 = 64;
+
+fn load_png(path: &std::path::Path) -> Option<(u32, u32, Vec<u8>)> {
+    let decoder = png::Decoder::new(std::fs::File::open(path).ok()?);
+    let mut reader = decoder.read_info().unwrap();
+    let mut image = vec![0; reader.output_buffer_size()];
+    let info = reader.next_frame(&mut image).unwrap();
+    assert_eq!(info.color_type, png::ColorType::Rgba);
+    image.truncate(info.buffer_size());
+    Some((info.width, info.height, image))
+}
+
+/// How a golden image is rendered, beyond the world and camera
+struct GoldenOptions {
+    render_mode: RenderMode,
+    lighting: Lighting,
+    environment: Environment,
+}
+
+impl Default for GoldenOptions {
+    fn default() -> Self {
+        Self {
+            render_mode: RenderMode::Perspective,
+            lighting: Lighting::default(),
+            environment: Environment::default(),
+        }
+    }
+}
+
+fn check_golden(name: &str, world: &mut World, camera: &Camera, options: GoldenOptions) {
+    let GoldenOptions {
+        render_mode,
+        lighting,
+        environment,
+    } = options;
+    let Some((device, queue)) = create_device() else {
+        eprintln!("skipping golden image `{name}`, no wgpu adapter is available");
+        return;
+    };
+
+    let mut renderer = Renderer::new(&device);
+    renderer.lighting = lighting;
+    renderer.environment = environment;
+    renderer.resize(&device, cgmath::vec2(WIDTH, HEIGHT));
+    let frames = match render_mode {
+        RenderMode::PathTraced => PATH_TRACED_SAMPLES,
+        _ => 1,
+    };
+    for _ in 0..frames {
+        renderer.render(
+            &device,
+            &queue,
+            world,
+            &render_mode.view_camera(camera),
+            render_mode,
+            None,
+        );
+    }
+    let image = renderer.read_main_texture(&device, &queue);
+
+    let path = std::path::Path::new(env!("CARGO_MANIFEST_DIR"))
+        .join("tests/golden")
+        .join(format!("{name}.png"));
+    if std::env::var_os("UPDATE_GOLDEN").is_some() {
+        tesseracts::save_png(&path, WIDTH, HEIGHT, &image).unwrap();
+        return;
+    }
+
+    let (width, height, expected) = load_png(&path).unwrap_or_else(|| {
+        panic!(
+            "missing golden image `{}`, run with UPDATE_GOLDEN=1 to create it",
+            path.display()
+        )
+    });
+    assert_eq!(
+        (width, height),
+        (WIDTH, HEIGHT),
+        "golden image `{name}` has the wrong size"
+    );
+
+    let different_pixels = image
+        .chunks(4)
+        .zip(expected.chunks(4))
+        .filter(|(actual, expected)| {
+            actual
+                .iter()
+                .zip(expected.iter())
+                .any(|(&a, &b)| a.abs_diff(b) > CHANNEL_TOLERANCE)
+        })
+        .count();
+    let allowed_pixels = ((WIDTH * HEIGHT) as f64 * PIXEL_TOLERANCE) as usize;
+    if different_pixels > allowed_pixels {
+        let actual_path = std::env::temp_dir().join(format!("{name}.actual.png"));
+        tesseracts::save_png(&actual_path, WIDTH, HEIGHT, &image).unwrap();
+        panic!(
+            "golden image `{name}` has {different_pixels} different pixels (at most {allowed_pixels} are allowed), the actual output was written to `{}`",
+            actual_path.display()
+        );
+    }
+}
+
+#[test]
+fn example_scene() {
+    check_golden(
+        "example_scene",
+        &mut World::example(),
+        &Camera::default(),
+        GoldenOptions::default(),
+    );
+}
+
+#[test]
+fn example_scene_rotated() {
+    let mut camera = Camera {
+        position: cgmath::vec4(4.0, 2.5, -2.0, 0.5),
+        ..Default::default()
+    };
+    camera.rotate(RotationPlane::XZ, -0.6);
+    camera.rotate(RotationPlane::YZ, -0.4);
+    camera.orthonormalize();
+    check_golden(
+        "example_scene_rotated",
+        &mut World::example(),
+        &camera,
+        GoldenOptions::default(),
+    );
+}
+
+#[test]
+fn example_scene_rotated_into_w() {
+    let mut camera = Camera {
+        position: cgmath::vec4(0.5, 0.5, -3.0, 0.5),
+        ..Default::default()
+    };
+    camera.rotate(RotationPlane::ZW, 0.15);
+    camera.orthonormalize();
+    check_golden(
+        "example_scene_rotated_into_w",
+        &mut World::example(),
+        &camera,
+        GoldenOptions::default(),
+    );
+}
+
+#[test]
+fn example_scene_slice() {
+    let mut camera = Camera {
+        position: cgmath::vec4(0.5, 0.5, -3.0, 0.5),
+        ..Default::default()
+    };
+    camera.rotate(RotationPlane::ZW, 0.5);
+    camera.orthonormalize();
+    check_golden(
+        "example_scene_slice",
+        &mut World::example(),
+        &camera,
+        GoldenOptions {
+            render_mode: RenderMode::Slice,
+            ..Default::default()
+        },
+    );
+}
+
+#[test]
+fn example_scene_horizontal_fov() {
+    let camera = Camera {
+        projection: Projection {
+            fov: cgmath::Deg(60.0),
+            fov_axis: FovAxis::Horizontal,
+            ..Default::default()
+        },
+        ..Default::default()
+    };
+    check_golden(
+        "example_scene_horizontal_fov",
+        &mut World::example(),
+        &camera,
+        GoldenOptions::default(),
+    );
+}
+
+#[test]
+fn example_scene_orthographic() {
+    let mut camera = Camera {
+        position: cgmath::vec4(4.0, 2.5, -2.0, 0.5),
+        projection: Projection {
+            mode: ProjectionMode::Orthographic,
+            orthographic_size: 6.0,
+            ..Default::default()
+        },
+        ..Default::default()
+    };
+    camera.rotate(RotationPlane::XZ, -0.6);
+    camera.rotate(RotationPlane::YZ, -0.4);
+    camera.orthonormalize();
+    check_golden(
+        "example_scene_orthographic",
+        &mut World::example(),
+        &camera,
+        GoldenOptions::default(),
+    );
+}
+
+#[test]
+fn example_scene_volumetric() {
+    check_golden(
+        "example_scene_volumetric",
+        &mut World::example(),
+        &Camera::default(),
+        GoldenOptions {
+            render_mode: RenderMode::Volumetric,
+            ..Default::default()
+        },
+    );
+}
+
+#[test]
+fn negative_chunks() {
+    let mut world = World::example();
+    for x in -6..-2 {
+        for w in -5..3 {
+            world.set_voxel(cgmath::vec4(x, -2, x + 4, w), Voxel::new((w & 1) as u32));
+        }
+    }
+    let camera = Camera {
+        position: cgmath::vec4(-1.5, 1.5, -6.0, 0.5),
+        ..Default::default()
+    };
+    check_golden(
+        "negative_chunks",
+        &mut world,
+        &camera,
+        GoldenOptions::default(),
+    );
+}
+
+/// The example scene standing on a floor within w = 0
+fn example_scene_on_floor() -> World {
+    let mut world = World::example();
+    for x in -3..=4 {
+        for z in -1..=4 {
+            world.set_voxel(cgmath::vec4(x, -1, z, 0), Voxel::new(1));
+        }
+    }
+    world
+}
+
+#[test]
+fn shadows() {
+    let mut world = example_scene_on_floor();
+    let camera = Camera {
+        position: cgmath::vec4(0.5, 2.5, -3.0, 0.5),
+        ..Default::default()
+    };
+    // the sun is over the camera and stays within w = 0, so the shadows fall whole on the floor behind the voxels
+    let lighting = Lighting {
+        sun_direction: cgmath::vec4(0.4, 0.8, -0.5, 0.0),
+        ..Lighting::default()
+    };
+    check_golden(
+        "shadows",
+        &mut world,
+        &camera,
+        GoldenOptions {
+            lighting,
+            ..Default::default()
+        },
+    );
+}
+
+#[test]
+fn ambient_occlusion() {
+    let camera = Camera {
+        position: cgmath::vec4(0.5, 2.0, -2.0, 0.5),
+        ..Default::default()
+    };
+    // only ambient light, so the occlusion is all there is to see
+    let lighting = Lighting {
+        sun_intensity: 0.0,
+        ambient: 1.0,
+        shadows: false,
+        ambient_occlusion_strength: 1.0,
+        ..Lighting::default()
+    };
+    check_golden(
+        "ambient_occlusion",
+        &mut example_scene_on_floor(),
+        &camera,
+        GoldenOptions {
+            lighting,
+            ..Default::default()
+        },
+    );
+}
+
+#[test]
+fn materials() {
+    let scene = Scene::load(concat!(env!("CARGO_MANIFEST_DIR"), "/scenes/materials.ron")).unwrap();
+    check_golden(
+        "materials",
+        &mut scene.world().unwrap(),
+        &scene.camera.camera(),
+        GoldenOptions::default(),
+    );
+}
+
+#[test]
+fn materials_path_traced() {
+    let scene = Scene::load(concat!(env!("CARGO_MANIFEST_DIR"), "/scenes/materials.ron")).unwrap();
+    check_golden(
+        "materials_path_traced",
+        &mut scene.world().unwrap(),
+        &scene.camera.camera(),
+        GoldenOptions {
+            render_mode: RenderMode::PathTraced,
+            ..Default::default()
+        },
+    );
+}
+
+#[test]
+fn fog() {
+    let mut world = World::new();
+    let generator = TerrainGenerator::new(3, TerrainMaterials::add_to(&mut world));
+    generator.fill(
+        &mut world,
+        cgmath::vec4(-6, -4, -6, -1),
+        cgmath::vec4(5, 3, 5, 0),
+    );
+    let mut camera = Camera {
+        position: cgmath::vec4(0.5, generator.height(0, 0, 0) as f32 + 2.5, 0.5, 0.5),
+        ..Default::default()
+    };
+    camera.rotate(RotationPlane::XZ, 0.5);
+    camera.rotate(RotationPlane::YZ, -0.3);
+    let environment = Environment {
+        fog: true,
+        fog_density: 0.06,
+        ..Environment::default()
+    };
+    check_golden(
+        "fog",
+        &mut world,
+        &camera,
+        GoldenOptions {
+            environment,
+            ..Default::default()
+        },
+    );
+}
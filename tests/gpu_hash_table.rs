@@ -0,0 +1,116 @@
+mod common;
+
+use common::{create_device, read_buffer};
+use tesseracts::{hash_key, GpuHashTable, REMOVED_ENTRY, UNUSED_ENTRY};
+
+/// Keys spread out over the whole range of `i32`, along with a few next to each other
+fn keys() -> Vec<cgmath::Vector4<i32>> {
+    let mut keys = (0..500)
+        .map(|i: i32| {
+            let spread = i.wrapping_mul(8_675_309);
+            cgmath::vec4(spread, -spread, i, spread.rotate_left(16))
+        })
+        .collect::<Vec<_>>();
+    keys.extend((1..5).map(|x| cgmath::vec4(x, 0, 0, 0)));
+    keys.push(cgmath::vec4(i32::MIN, i32::MAX, i32::MIN, i32::MAX));
+    keys
+}
+
+#[test]
+fn set_get_and_remove() {
+    let Some((device, _)) = create_device() else {
+        eprintln!("skipping, no wgpu adapter is available");
+        return;
+    };
+
+    let mut table = GpuHashTable::new(&device, "Test Hash Table");
+    let keys = keys();
+    for (i, &key) in keys.iter().enumerate() {
+        table.set(key, 0, Some(i as u32));
+    }
+    // the same key at another level is another entry
+    table.set(keys[0], 1, Some(1234));
+    for (i, &key) in keys.iter().enumerate() {
+        assert_eq!(table.get(key, 0), Some(i as u32));
+    }
+    assert_eq!(table.get(keys[0], 1), Some(1234));
+    assert_eq!(table.get(keys[1], 1), None);
+    // the size only depends on how many keys there are
+    assert!(table.capacity() <= keys.len() * 8);
+
+    for &key in keys.iter().step_by(2) {
+        table.set(key, 0, None);
+    }
+    for (i, &key) in keys.iter().enumerate() {
+        let expected = (i % 2 == 1).then_some(i as u32);
+        assert_eq!(table.get(key, 0), expected);
+    }
+
+    // removing and adding keys over and over reuses the removed entries, or rebuilds without them
+    let capacity = table.capacity();
+    for round in 0..10 {
+        for &key in keys.iter().step_by(2) {
+            table.set(key, 0, Some(round));
+        }
+        for &key in keys.iter().step_by(2) {
+            table.set(key, 0, None);
+        }
+    }
+    assert!(table.capacity() <= capacity * 2);
+    assert_eq!(table.get(keys[1], 0), Some(1));
+}
+
+#[test]
+fn upload_matches_the_table() {
+    let Some((device, queue)) = create_device() else {
+        eprintln!("skipping, no wgpu adapter is available");
+        return;
+    };
+
+    let mut table = GpuHashTable::new(&device, "Test Hash Table");
+    let keys = keys();
+    // a first upload of everything, then of only the entries that changed
+    for &key in &keys[..100] {
+        table.set(key, 2, Some(7));
+    }
+    table.upload(&device, &queue);
+    for &key in &keys[..50] {
+        table.set(key, 2, None);
+    }
+    table.set(keys[100], 2, Some(8));
+    table.upload(&device, &queue);
+
+    let data = read_buffer(&device, &queue, table.buffer());
+    let word = |offset: usize| u32::from_le_bytes(data[offset..offset + 4].try_into().unwrap());
+    let mask = word(0) as usize;
+    assert_eq!(mask + 1, table.capacity());
+    // the entries start after the header, and are 32 bytes each
+    let find = |key: cgmath::Vector4<i32>| {
+        let mut index = hash_key(key, 2) as usize & mask;
+        loop {
+            let entry = 16 + index * 32;
+            let entry_key = cgmath::vec4(
+                word(entry) as i32,
+                word(entry + 4) as i32,
+                word(entry + 8) as i32,
+                word(entry + 12) as i32,
+            );
+            let value = word(entry + 20);
+            if value == UNUSED_ENTRY {
+                return None;
+            }
+            if entry_key == key && word(entry + 16) == 2 {
+                return (value != REMOVED_ENTRY).then_some(value);
+            }
+            index = (index + 1) & mask;
+        }
+    };
+    for &key in &keys[..50] {
+        assert_eq!(find(key), None);
+    }
+    for &key in &keys[50..100] {
+        assert_eq!(find(key), Some(7));
+    }
+    assert_eq!(find(keys[100]), Some(8));
+    assert_eq!(find(keys[101]), None);
+}
@@ -0,0 +1,180 @@
+//! Checks that incrementally updating the GPU copy of a world renders the same as uploading it from scratch
+
+mod common;
+
+use common::create_device;
+use eframe::wgpu;
+use tesseracts::{
+    Camera, Chunk, Material, RenderMode, Renderer, TerrainGenerator, TerrainMaterials, Voxel, World,
+};
+
+const WIDTH: u32 = 96;
+const HEIGHT: u32 = 64;
+
+fn render(
+    device: &wgpu::Device,
+    queue: &wgpu::Queue,
+    renderer: &mut Renderer,
+    world: &mut World,
+    camera: &Camera,
+) -> Vec<u8> {
+    renderer.resize(device, cgmath::vec2(WIDTH, HEIGHT));
+    renderer.render(device, queue, world, camera, RenderMode::Perspective, None);
+    renderer.read_main_texture(device, queue)
+}
+
+/// Copies the materials and chunks of `world` into a new world, which has never been uploaded
+fn copy_world(world: &World) -> World {
+    let mut copy = World::new();
+    for &material in world.materials() {
+        copy.add_material(material);
+    }
+    for (position, chunk) in world.chunks() {
+        copy.insert_chunk(position, chunk.clone());
+    }
+    copy
+}
+
+#[test]
+fn incremental_updates_match_full_upload() {
+    let Some((device, queue)) = create_device() else {
+        eprintln!("skipping, no wgpu adapter is available");
+        return;
+    };
+
+    let mut world = World::new();
+    let generator = TerrainGenerator::new(3, TerrainMaterials::add_to(&mut world));
+    let camera = Camera {
+        position: cgmath::vec4(0.5, generator.height(0, 0, 0) as f32 + 3.5, -6.0, 0.5),
+        ..Default::default()
+    };
+
+    let mut renderer = Renderer::new(&device);
+    let edits: [&dyn Fn(&mut World); 8] = [
+        &|world| {
+            generator.fill(world, cgmath::vec4(-1, -4, -1, 0), cgmath::vec4(0, 3, 0, 0));
+        },
+        // new chunks that grow the bounding box and the voxel pool
+        &|world| {
+            generator.fill(world, cgmath::vec4(-3, -4, -1, 0), cgmath::vec4(2, 3, 2, 0));
+        },
+        // edits within existing chunks, and a new chunk within the bounding box
+        &|world| {
+            let height = generator.height(0, 0, 0);
+            world.set_voxel(cgmath::vec4(0, height, 0, 0), Voxel::EMPTY);
+            world.set_voxel(cgmath::vec4(1, height + 1, 1, 0), Voxel::new(4));
+            let red = world.add_material(Material::new(cgmath::vec3(1.0, 0.0, 0.0)));
+            world.set_voxel(cgmath::vec4(-1, height + 2, -1, 0), Voxel::new(red));
+        },
+        // removed chunks within the bounding box free their slots for the next ones
+        &|world| {
+            for x in -2..0 {
+                for y in -4..=3 {
+                    world.remove_chunk(cgmath::vec4(x, y, 0, 0));
+                }
+            }
+            let mut chunk = Chunk::new();
+            chunk.data.fill(Voxel::new(2));
+            world.insert_chunk(cgmath::vec4(-2, -1, 0, 0), chunk);
+        },
+        // emptying whole cells of the occupancy within the bounding box, in front of the camera
+        &|world| {
+            for x in 0..2 {
+                for y in -4..=3 {
+                    world.remove_chunk(cgmath::vec4(x, y, -1, 0));
+                }
+            }
+        },
+        // and filling one of them again
+        &|world| {
+            let mut chunk = Chunk::new();
+            chunk.data.fill(Voxel::new(2));
+            let camera_chunk = World::split_position(camera.position.map(|x| x.floor() as i32)).0;
+            world.insert_chunk(cgmath::vec4(0, camera_chunk.y, -1, 0), chunk);
+        },
+        // removing most of the chunks compacts the voxel pool, moving the rest into other slots
+        &|world| {
+            let positions = world
+                .chunks()
+                .map(|(position, _)| position)
+                .filter(|position| position.x != 1)
+                .collect::<Vec<_>>();
+            for position in positions {
+                world.remove_chunk(position);
+            }
+            world.set_voxel(cgmath::vec4(5, 0, 5, 0), Voxel::new(3));
+        },
+        &|world| {
+            let positions = world
+                .chunks()
+                .map(|(position, _)| position)
+                .collect::<Vec<_>>();
+            for position in positions {
+                world.remove_chunk(position);
+            }
+        },
+    ];
+    for (i, edit) in edits.iter().enumerate() {
+        edit(&mut world);
+        let incremental = render(&device, &queue, &mut renderer, &mut world, &camera);
+        let full = render(
+            &device,
+            &queue,
+            &mut Renderer::new(&device),
+            &mut copy_world(&world),
+            &camera,
+        );
+        assert!(incremental == full, "renders differ after edit {i}");
+    }
+}
+
+#[test]
+fn far_apart_chunks_take_bounded_memory() {
+    let Some((device, queue)) = create_device() else {
+        eprintln!("skipping, no wgpu adapter is available");
+        return;
+    };
+
+    let mut world = World::new();
+    let generator = TerrainGenerator::new(3, TerrainMaterials::add_to(&mut world));
+    generator.fill(
+        &mut world,
+        cgmath::vec4(-1, -4, -1, 0),
+        cgmath::vec4(0, 3, 0, 0),
+    );
+    let camera = Camera {
+        position: cgmath::vec4(0.5, generator.height(0, 0, 0) as f32 + 3.5, -6.0, 0.5),
+        ..Default::default()
+    };
+    let mut renderer = Renderer::new(&device);
+    let near = render(&device, &queue, &mut renderer, &mut world, &camera);
+
+    // thousands of chunks away from the rest, and from each other, out to the edges of `i32`
+    let mut chunk = Chunk::new();
+    chunk.data.fill(Voxel::new(2));
+    let far = [
+        cgmath::vec4(5000, 0, 0, 0),
+        cgmath::vec4(-3000, 7000, 2000, -9000),
+        cgmath::vec4(i32::MAX / 4, i32::MIN / 4, i32::MAX / 4, i32::MIN / 4),
+    ];
+    for position in far {
+        world.insert_chunk(position, chunk.clone());
+    }
+    let incremental = render(&device, &queue, &mut renderer, &mut world, &camera);
+    let full = render(
+        &device,
+        &queue,
+        &mut Renderer::new(&device),
+        &mut copy_world(&world),
+        &camera,
+    );
+    assert!(incremental == near, "far chunks changed the render");
+    assert!(full == near, "far chunks changed the render");
+
+    // the far chunks only add their own voxels and table entries, rather than the space between them
+    assert!(
+        renderer.gpu_world().buffer_size() < 1 << 20,
+        "{} bytes",
+        renderer.gpu_world().buffer_size()
+    );
+}
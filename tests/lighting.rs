@@ -0,0 +1,330 @@
+mod common;
+
+use common::assert_close;
+use tesseracts::{
+    get_ambient_occlusion, get_block_light, get_hit_color, get_intersection, is_in_shadow,
+    Lighting, Material, Ray, Voxel, World,
+};
+
+const WHITE: cgmath::Vector3<f32> = cgmath::vec3(1.0, 1.0, 1.0);
+
+#[test]
+fn lambertian() {
+    let lighting = Lighting {
+        sun_direction: cgmath::vec4(0.0, 2.0, 0.0, 0.0),
+        sun_intensity: 0.8,
+        ambient: 0.2,
+        shadows: true,
+        ambient_occlusion: false,
+        ambient_occlusion_strength: 0.0,
+        max_bounces: 0,
+        block_light: 0.0,
+    };
+    // facing the sun, at 60 degrees from it, side on, and facing away
+    assert_close(
+        lighting.shade(WHITE, cgmath::vec4(0.0, 1.0, 0.0, 0.0), false),
+        WHITE,
+    );
+    assert_close(
+        lighting.shade(WHITE, cgmath::vec4(0.0, 0.5, 0.0, 0.75f32.sqrt()), false),
+        WHITE * 0.6,
+    );
+    assert_close(
+        lighting.shade(WHITE, cgmath::vec4(0.0, 0.0, 0.0, 1.0), false),
+        WHITE * 0.2,
+    );
+    assert_close(
+        lighting.shade(WHITE, cgmath::vec4(0.0, -1.0, 0.0, 0.0), false),
+        WHITE * 0.2,
+    );
+    // the light scales the material color
+    assert_close(
+        lighting.shade(
+            cgmath::vec3(1.0, 0.5, 0.0),
+            cgmath::vec4(0.0, 1.0, 0.0, 0.0),
+            false,
+        ),
+        cgmath::vec3(1.0, 0.5, 0.0),
+    );
+}
+
+#[test]
+fn sun_along_w() {
+    let lighting = Lighting {
+        sun_direction: cgmath::vec4(0.0, 0.0, 0.0, -1.0),
+        sun_intensity: 1.0,
+        ambient: 0.0,
+        shadows: true,
+        ambient_occlusion: false,
+        ambient_occlusion_strength: 0.0,
+        max_bounces: 0,
+        block_light: 0.0,
+    };
+    assert_close(
+        lighting.shade(WHITE, cgmath::vec4(0.0, 0.0, 0.0, -1.0), false),
+        WHITE,
+    );
+    assert_close(
+        lighting.shade(WHITE, cgmath::vec4(0.0, 1.0, 0.0, 0.0), false),
+        cgmath::vec3(0.0, 0.0, 0.0),
+    );
+}
+
+#[test]
+fn no_sun_direction() {
+    let lighting = Lighting {
+        sun_direction: cgmath::vec4(0.0, 0.0, 0.0, 0.0),
+        sun_intensity: 1.0,
+        ambient: 0.5,
+        shadows: true,
+        ambient_occlusion: false,
+        ambient_occlusion_strength: 0.0,
+        max_bounces: 0,
+        block_light: 0.0,
+    };
+    assert_close(
+        lighting.shade(WHITE, cgmath::vec4(1.0, 0.0, 0.0, 0.0), false),
+        WHITE * 0.5,
+    );
+}
+
+#[test]
+fn hit_color_uses_the_entered_face() {
+    let mut world = World::new();
+    let material = world.add_material(Material::new(WHITE));
+    world.set_voxel(cgmath::vec4(0, 0, 0, 0), Voxel::new(material));
+    let lighting = Lighting {
+        sun_direction: cgmath::vec4(0.0, 1.0, 0.0, 0.0),
+        sun_intensity: 1.0,
+        ambient: 0.25,
+        shadows: true,
+        ambient_occlusion: false,
+        ambient_occlusion_strength: 0.0,
+        max_bounces: 0,
+        block_light: 0.0,
+    };
+
+    let color_from = |origin: cgmath::Vector4<f32>, direction: cgmath::Vector4<f32>| {
+        let hit = get_intersection(&world, Ray { origin, direction }, 100.0).unwrap();
+        get_hit_color(&world, &lighting, &hit, 100.0)
+    };
+    // the top is lit, the bottom only gets the ambient light
+    assert_close(
+        color_from(
+            cgmath::vec4(0.5, 3.5, 0.5, 0.5),
+            cgmath::vec4(0.0, -1.0, 0.0, 0.0),
+        ),
+        WHITE * 1.25,
+    );
+    assert_close(
+        color_from(
+            cgmath::vec4(0.5, -3.5, 0.5, 0.5),
+            cgmath::vec4(0.0, 1.0, 0.0, 0.0),
+        ),
+        WHITE * 0.25,
+    );
+}
+
+#[test]
+fn shadowed_faces_only_get_ambient() {
+    assert_close(
+        Lighting::default().shade(WHITE, cgmath::vec4(0.0, 1.0, 0.0, 0.0), true),
+        WHITE * Lighting::default().ambient,
+    );
+}
+
+/// Whether the top of the voxel at `position` is in shadow, hitting it from straight above
+fn top_in_shadow(world: &World, lighting: &Lighting, position: cgmath::Vector4<i32>) -> bool {
+    let ray = Ray {
+        origin: position.cast().unwrap() + cgmath::vec4(0.5, 1.5, 0.5, 0.5),
+        direction: cgmath::vec4(0.0, -1.0, 0.0, 0.0),
+    };
+    let hit = get_intersection(world, ray, 100.0).unwrap();
+    assert_eq!(hit.normal, cgmath::vec4(0.0, 1.0, 0.0, 0.0));
+    is_in_shadow(world, lighting, &hit, 100.0)
+}
+
+#[test]
+fn voxels_shadow_each_other() {
+    let mut world = World::new();
+    let material = world.add_material(Material::new(WHITE));
+    // a floor along x, with a voxel floating above the middle of it
+    for x in -4..=3 {
+        world.set_voxel(cgmath::vec4(x, 0, 0, 0), Voxel::new(material));
+    }
+    world.set_voxel(cgmath::vec4(0, 5, 0, 0), Voxel::new(material));
+    let mut lighting = Lighting {
+        sun_direction: cgmath::vec4(0.0, 1.0, 0.0, 0.0),
+        ..Lighting::default()
+    };
+
+    assert!(top_in_shadow(&world, &lighting, cgmath::vec4(0, 0, 0, 0)));
+    assert!(!top_in_shadow(&world, &lighting, cgmath::vec4(1, 0, 0, 0)));
+    assert!(!top_in_shadow(&world, &lighting, cgmath::vec4(0, 5, 0, 0)));
+
+    // the shadow falls along the sun direction, including along w
+    lighting.sun_direction = cgmath::vec4(1.0, 1.0, 0.0, 0.0);
+    assert!(!top_in_shadow(&world, &lighting, cgmath::vec4(0, 0, 0, 0)));
+    assert!(top_in_shadow(&world, &lighting, cgmath::vec4(-4, 0, 0, 0)));
+    world.set_voxel(cgmath::vec4(0, 3, 0, 2), Voxel::new(material));
+    lighting.sun_direction = cgmath::vec4(0.0, 1.0, 0.0, 1.0);
+    assert!(top_in_shadow(&world, &lighting, cgmath::vec4(0, 0, 0, 0)));
+
+    lighting.shadows = false;
+    assert!(!top_in_shadow(&world, &lighting, cgmath::vec4(0, 0, 0, 0)));
+}
+
+#[test]
+fn faces_dont_shadow_themselves() {
+    let mut world = World::new();
+    let material = world.add_material(Material::new(WHITE));
+    // on either side of 0, since faces at negative positions round the other way
+    for position in [cgmath::vec4(0, 0, 0, 0), cgmath::vec4(-7, -7, -7, -7)] {
+        world.set_voxel(position, Voxel::new(material));
+        let center = position.cast::<f32>().unwrap() + cgmath::vec4(0.5, 0.5, 0.5, 0.5);
+        for axis in 0..4 {
+            for sign in [-1.0, 1.0] {
+                let mut normal = cgmath::vec4(0.0, 0.0, 0.0, 0.0);
+                normal[axis] = sign;
+                // the sun is slightly off the normal, so the shadow ray runs along the face
+                let lighting = Lighting {
+                    sun_direction: normal + cgmath::vec4(0.1, 0.1, 0.1, 0.1),
+                    ..Lighting::default()
+                };
+                let ray = Ray {
+                    origin: center + normal * 3.0,
+                    direction: -normal,
+                };
+                let hit = get_intersection(&world, ray, 100.0).unwrap();
+                assert_eq!(hit.normal, normal);
+                assert!(!is_in_shadow(&world, &lighting, &hit, 100.0));
+            }
+        }
+    }
+}
+
+/// The ambient occlusion where a ray straight down hits the top of the voxel at `position`,
+/// at `offset` across the face
+fn top_occlusion(
+    world: &World,
+    position: cgmath::Vector4<i32>,
+    offset: cgmath::Vector4<f32>,
+) -> f32 {
+    let ray = Ray {
+        origin: position.cast().unwrap() + cgmath::vec4(offset.x, 1.5, offset.z, offset.w),
+        direction: cgmath::vec4(0.0, -1.0, 0.0, 0.0),
+    };
+    let hit = get_intersection(world, ray, 100.0).unwrap();
+    assert_eq!(hit.voxel_position, position);
+    get_ambient_occlusion(world, &hit)
+}
+
+#[test]
+fn ambient_occlusion() {
+    let mut world = World::new();
+    let material = world.add_material(Material::new(WHITE));
+    let center = cgmath::vec4(0.5, 0.0, 0.5, 0.5);
+    world.set_voxel(cgmath::vec4(0, 0, 0, 0), Voxel::new(material));
+    assert_eq!(top_occlusion(&world, cgmath::vec4(0, 0, 0, 0), center), 0.0);
+
+    // a wall along +x, which only voxels level with the voxel in front of the face count towards
+    for y in 0..3 {
+        for z in -1..=1 {
+            for w in -1..=1 {
+                world.set_voxel(cgmath::vec4(1, y, z, w), Voxel::new(material));
+            }
+        }
+    }
+    let occlusion = |x| {
+        top_occlusion(
+            &world,
+            cgmath::vec4(0, 0, 0, 0),
+            cgmath::vec4(x, 0.0, 0.5, 0.5),
+        )
+    };
+    assert!((occlusion(0.5) - 2.0 / 7.0).abs() < 1e-5);
+    // it gets darker towards the wall
+    assert!(occlusion(0.1) < occlusion(0.5));
+    assert!(occlusion(0.5) < occlusion(0.9));
+
+    // a face closed off along every axis is fully occluded, wherever the hit is
+    for x in -1..=1 {
+        for z in -1..=1 {
+            for w in -1..=1 {
+                if (x, z, w) != (0, 0, 0) {
+                    world.set_voxel(cgmath::vec4(x, 1, z, w), Voxel::new(material));
+                }
+            }
+        }
+    }
+    for offset in [center, cgmath::vec4(0.1, 0.0, 0.8, 0.3)] {
+        assert!((top_occlusion(&world, cgmath::vec4(0, 0, 0, 0), offset) - 1.0).abs() < 1e-5);
+    }
+}
+
+#[test]
+fn ambient_occlusion_darkens_the_hit_color() {
+    let mut world = World::new();
+    let material = world.add_material(Material::new(WHITE));
+    world.set_voxel(cgmath::vec4(0, 0, 0, 0), Voxel::new(material));
+    world.set_voxel(cgmath::vec4(1, 1, 0, 0), Voxel::new(material));
+    let mut lighting = Lighting {
+        sun_direction: cgmath::vec4(0.0, 1.0, 0.0, 0.0),
+        sun_intensity: 0.0,
+        ambient: 1.0,
+        shadows: false,
+        ambient_occlusion: true,
+        ambient_occlusion_strength: 0.7,
+        max_bounces: 0,
+        block_light: 0.0,
+    };
+    let ray = Ray {
+        origin: cgmath::vec4(0.5, 1.5, 0.5, 0.5),
+        direction: cgmath::vec4(0.0, -1.0, 0.0, 0.0),
+    };
+    let hit = get_intersection(&world, ray, 100.0).unwrap();
+    // at the center of the face, a voxel next to the one in front of it has half of its weight
+    let occlusion = 0.5 / 7.0;
+    assert_close(
+        get_hit_color(&world, &lighting, &hit, 100.0),
+        WHITE * (1.0 - 0.7 * occlusion),
+    );
+    lighting.ambient_occlusion = false;
+    assert_close(get_hit_color(&world, &lighting, &hit, 100.0), WHITE);
+}
+
+#[test]
+fn block_light_brightens_the_hit_color() {
+    let mut world = World::new();
+    let gray = cgmath::vec3(0.5, 0.5, 0.5);
+    let floor = world.add_material(Material::new(gray));
+    let lamp = world.add_material(Material {
+        emission: 1.0,
+        ..Material::new(WHITE)
+    });
+    world.set_voxel(cgmath::vec4(0, 0, 0, 0), Voxel::new(floor));
+    world.set_voxel(cgmath::vec4(2, 1, 0, 0), Voxel::new(lamp));
+    let mut lighting = Lighting {
+        sun_direction: cgmath::vec4(0.0, 1.0, 0.0, 0.0),
+        sun_intensity: 0.0,
+        ambient: 0.2,
+        shadows: false,
+        ambient_occlusion: false,
+        ambient_occlusion_strength: 0.0,
+        max_bounces: 0,
+        block_light: 0.5,
+    };
+    let ray = Ray {
+        origin: cgmath::vec4(0.5, 1.5, 0.5, 0.5),
+        direction: cgmath::vec4(0.0, -1.0, 0.0, 0.0),
+    };
+    let hit = get_intersection(&world, ray, 100.0).unwrap();
+    // the voxel in front of the face is two steps from the lamp
+    assert!((get_block_light(&world, &hit) - 13.0 / 15.0).abs() < 1e-5);
+    assert_close(
+        get_hit_color(&world, &lighting, &hit, 100.0),
+        gray * 0.2 + gray * 0.5 * 13.0 / 15.0,
+    );
+    lighting.block_light = 0.0;
+    assert_close(get_hit_color(&world, &lighting, &hit, 100.0), gray * 0.2);
+}
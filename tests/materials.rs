@@ -0,0 +1,218 @@
+mod common;
+
+use common::assert_close;
+use tesseracts::{ray_trace, Camera, Environment, Lighting, Material, Ray, Voxel, World};
+
+const WHITE: cgmath::Vector3<f32> = cgmath::vec3(1.0, 1.0, 1.0);
+const RED: cgmath::Vector3<f32> = cgmath::vec3(1.0, 0.0, 0.0);
+const GREEN: cgmath::Vector3<f32> = cgmath::vec3(0.0, 1.0, 0.0);
+const BLACK: cgmath::Vector3<f32> = cgmath::vec3(0.0, 0.0, 0.0);
+
+/// Only ambient light, so every face shows its color whichever way it points
+fn flat_lighting(max_bounces: u32) -> Lighting {
+    Lighting {
+        sun_intensity: 0.0,
+        ambient: 1.0,
+        shadows: false,
+        ambient_occlusion: false,
+        max_bounces,
+        ..Lighting::default()
+    }
+}
+
+/// A black sky with no sun disc, so rays that miss add nothing
+fn black_environment() -> Environment {
+    Environment {
+        zenith_color: BLACK,
+        horizon_color: BLACK,
+        ground_color: BLACK,
+        sun_disc_radius: cgmath::Deg(0.0),
+        ..Environment::default()
+    }
+}
+
+fn trace(
+    world: &World,
+    lighting: &Lighting,
+    origin: cgmath::Vector4<f32>,
+    direction: cgmath::Vector4<f32>,
+) -> cgmath::Vector3<f32> {
+    ray_trace(
+        world,
+        &Camera::default(),
+        lighting,
+        &black_environment(),
+        Ray { origin, direction },
+    )
+}
+
+#[test]
+fn mirrors() {
+    let mut world = World::new();
+    let mirror = world.add_material(Material {
+        reflectivity: 1.0,
+        ..Material::new(WHITE)
+    });
+    let red = world.add_material(Material::new(RED));
+    let green = world.add_material(Material::new(GREEN));
+    // a mirror facing -z with red behind the camera, and one facing -w with green behind it
+    world.set_voxel(cgmath::vec4(0, 0, 0, 0), Voxel::new(mirror));
+    world.set_voxel(cgmath::vec4(0, 0, -4, 0), Voxel::new(red));
+    world.set_voxel(cgmath::vec4(5, 0, 0, 3), Voxel::new(mirror));
+    world.set_voxel(cgmath::vec4(5, 0, 0, -2), Voxel::new(green));
+
+    let z = cgmath::vec4(0.0, 0.0, 1.0, 0.0);
+    let w = cgmath::vec4(0.0, 0.0, 0.0, 1.0);
+    assert_close(
+        trace(
+            &world,
+            &flat_lighting(1),
+            cgmath::vec4(0.5, 0.5, -1.5, 0.5),
+            z,
+        ),
+        RED,
+    );
+    assert_close(
+        trace(
+            &world,
+            &flat_lighting(1),
+            cgmath::vec4(5.5, 0.5, 0.5, 0.5),
+            w,
+        ),
+        GREEN,
+    );
+    // without bounces the mirror shows nothing, since all of its light is reflected
+    assert_close(
+        trace(
+            &world,
+            &flat_lighting(0),
+            cgmath::vec4(0.5, 0.5, -1.5, 0.5),
+            z,
+        ),
+        BLACK,
+    );
+
+    // partly reflective surfaces mix their own color with what they reflect
+    world.set_material(
+        mirror,
+        Material {
+            reflectivity: 0.25,
+            ..Material::new(WHITE)
+        },
+    );
+    assert_close(
+        trace(
+            &world,
+            &flat_lighting(1),
+            cgmath::vec4(0.5, 0.5, -1.5, 0.5),
+            z,
+        ),
+        WHITE * 0.75 + RED * 0.25,
+    );
+}
+
+#[test]
+fn glass_is_tinted_once() {
+    let mut world = World::new();
+    let glass = world.add_material(Material {
+        transparency: 1.0,
+        ..Material::new(cgmath::vec3(1.0, 0.5, 0.25))
+    });
+    let white = world.add_material(Material::new(WHITE));
+    // a block of glass several voxels thick, whose inner faces aren't surfaces
+    for z in 0..3 {
+        for x in -1..=1 {
+            world.set_voxel(cgmath::vec4(x, 0, z, 0), Voxel::new(glass));
+        }
+    }
+    world.set_voxel(cgmath::vec4(0, 0, 5, 0), Voxel::new(white));
+
+    let origin = cgmath::vec4(0.5, 0.5, -1.5, 0.5);
+    let z = cgmath::vec4(0.0, 0.0, 1.0, 0.0);
+    assert_close(
+        trace(&world, &flat_lighting(2), origin, z),
+        cgmath::vec3(1.0, 0.5, 0.25),
+    );
+    // getting into and out of the glass are a bounce each
+    assert_close(trace(&world, &flat_lighting(1), origin, z), BLACK);
+}
+
+#[test]
+fn refraction_bends_rays() {
+    let mut world = World::new();
+    let glass = world.add_material(Material {
+        transparency: 1.0,
+        refractive_index: 1.5,
+        ..Material::new(WHITE)
+    });
+    let red = world.add_material(Material::new(RED));
+    let green = world.add_material(Material::new(GREEN));
+    // a slab of glass from z = 0 to 5, and a wall at z = 8 which is red where a bent ray hits it,
+    // and green where a straight one would
+    for x in -2..=12 {
+        for z in 0..5 {
+            world.set_voxel(cgmath::vec4(x, 0, z, 0), Voxel::new(glass));
+        }
+    }
+    world.set_voxel(cgmath::vec4(6, 0, 8, 0), Voxel::new(red));
+    for x in 8..=10 {
+        world.set_voxel(cgmath::vec4(x, 0, 8, 0), Voxel::new(green));
+    }
+
+    let origin = cgmath::vec4(0.0, 0.5, -0.5, 0.5);
+    let direction = cgmath::vec4(1.0, 0.0, 1.0, 0.0) / 2f32.sqrt();
+    assert_close(trace(&world, &flat_lighting(4), origin, direction), RED);
+    world.set_material(
+        glass,
+        Material {
+            transparency: 1.0,
+            refractive_index: 1.0,
+            ..Material::new(WHITE)
+        },
+    );
+    assert_close(trace(&world, &flat_lighting(4), origin, direction), GREEN);
+}
+
+#[test]
+fn total_internal_reflection() {
+    let mut world = World::new();
+    let glass = world.add_material(Material {
+        transparency: 1.0,
+        refractive_index: 1.5,
+        ..Material::new(WHITE)
+    });
+    let red = world.add_material(Material::new(RED));
+    // a column of glass one voxel across, with red at the end of it
+    for z in 0..4 {
+        world.set_voxel(cgmath::vec4(0, 0, z, 0), Voxel::new(glass));
+    }
+    world.set_voxel(cgmath::vec4(0, 0, 4, 0), Voxel::new(red));
+
+    // the ray enters through the end at 45 degrees, so it hits the sides of the column too steeply to leave
+    let origin = cgmath::vec4(0.1, 0.5, -0.5, 0.5);
+    let direction = cgmath::vec4(1.0, 0.0, 1.0, 0.0) / 2f32.sqrt();
+    assert_close(trace(&world, &flat_lighting(8), origin, direction), RED);
+}
+
+#[test]
+fn emission() {
+    let mut world = World::new();
+    let lamp = world.add_material(Material {
+        emission: 2.0,
+        ..Material::new(cgmath::vec3(0.5, 0.25, 0.0))
+    });
+    world.set_voxel(cgmath::vec4(0, 0, 0, 0), Voxel::new(lamp));
+
+    let dark = Lighting {
+        ambient: 0.0,
+        ..flat_lighting(0)
+    };
+    let origin = cgmath::vec4(0.5, 0.5, -1.5, 0.5);
+    let z = cgmath::vec4(0.0, 0.0, 1.0, 0.0);
+    assert_close(trace(&world, &dark, origin, z), cgmath::vec3(1.0, 0.5, 0.0));
+    // on top of any light it gets
+    assert_close(
+        trace(&world, &flat_lighting(0), origin, z),
+        cgmath::vec3(1.5, 0.75, 0.0),
+    );
+}
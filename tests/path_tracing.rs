@@ -0,0 +1,217 @@
+//! Checks that `RenderMode::PathTraced` adds up samples while nothing changes, and what they converge to
+
+mod common;
+
+use common::create_device;
+use eframe::wgpu;
+use tesseracts::{
+    Camera, Environment, Lighting, Material, RenderMode, Renderer, RotationPlane, Voxel, World,
+};
+
+const WIDTH: u32 = 48;
+const HEIGHT: u32 = 32;
+
+/// A light gray floor with its top at y = 1, and a white ceiling on top of it at y = 3 if `emission` is given
+fn floor_world(emission: Option<f32>) -> World {
+    let mut world = World::new();
+    let floor = world.add_material(Material::new(cgmath::vec3(0.8, 0.8, 0.8)));
+    let ceiling = world.add_material(Material {
+        emission: emission.unwrap_or(0.0),
+        ..Material::new(cgmath::vec3(1.0, 1.0, 1.0))
+    });
+    for x in -8..8 {
+        for z in -8..8 {
+            for w in -8..8 {
+                world.set_voxel(cgmath::vec4(x, 0, z, w), Voxel::new(floor));
+                if emission.is_some() {
+                    world.set_voxel(cgmath::vec4(x, 3, z, w), Voxel::new(ceiling));
+                }
+            }
+        }
+    }
+    world
+}
+
+/// Looking straight down at the floor from just below the ceiling
+fn camera_above_floor() -> Camera {
+    let mut camera = Camera {
+        position: cgmath::vec4(0.5, 2.5, 0.5, 0.5),
+        ..Default::default()
+    };
+    camera.rotate(RotationPlane::YZ, -std::f32::consts::FRAC_PI_2);
+    camera
+}
+
+/// Renders `samples` frames in a row, and returns the average of each channel across the final image
+fn render(
+    device: &wgpu::Device,
+    queue: &wgpu::Queue,
+    renderer: &mut Renderer,
+    world: &mut World,
+    samples: u32,
+) -> cgmath::Vector3<f32> {
+    renderer.resize(device, cgmath::vec2(WIDTH, HEIGHT));
+    for _ in 0..samples {
+        renderer.render(
+            device,
+            queue,
+            world,
+            &camera_above_floor(),
+            RenderMode::PathTraced,
+            None,
+        );
+    }
+    let image = renderer.read_main_texture(device, queue);
+    let mut sum = cgmath::vec3(0.0, 0.0, 0.0);
+    for pixel in image.chunks(4) {
+        sum += cgmath::vec3(pixel[0], pixel[1], pixel[2])
+            .cast::<f32>()
+            .unwrap()
+            / 255.0;
+    }
+    sum / (WIDTH * HEIGHT) as f32
+}
+
+/// The same color in every direction, without a sun disc
+fn uniform_sky(brightness: f32) -> Environment {
+    let color = cgmath::vec3(brightness, brightness, brightness);
+    Environment {
+        zenith_color: color,
+        horizon_color: color,
+        ground_color: color,
+        sun_disc_radius: cgmath::Deg(0.0),
+        ..Environment::default()
+    }
+}
+
+fn assert_channels_close(actual: cgmath::Vector3<f32>, expected: f32, tolerance: f32) {
+    assert!(
+        (0..3).all(|i| (actual[i] - expected).abs() <= tolerance),
+        "expected {expected} in every channel, got {actual:?}"
+    );
+}
+
+#[test]
+fn samples_reset_when_anything_changes() {
+    let Some((device, queue)) = create_device() else {
+        eprintln!("skipping, no wgpu adapter is available");
+        return;
+    };
+
+    let mut world = floor_world(None);
+    let mut renderer = Renderer::new(&device);
+    renderer.resize(&device, cgmath::vec2(WIDTH, HEIGHT));
+    let mut camera = camera_above_floor();
+    let render = |renderer: &mut Renderer, world: &mut World, camera: &Camera, mode| {
+        renderer.render(&device, &queue, world, camera, mode, None);
+        renderer.samples()
+    };
+
+    for samples in 1..=3 {
+        // as the app does every frame, which mustn't count as the camera moving
+        camera.orthonormalize();
+        assert_eq!(
+            render(&mut renderer, &mut world, &camera, RenderMode::PathTraced),
+            samples
+        );
+    }
+
+    camera.position.x += 1.0;
+    assert_eq!(
+        render(&mut renderer, &mut world, &camera, RenderMode::PathTraced),
+        1
+    );
+    renderer.lighting.ambient += 0.1;
+    assert_eq!(
+        render(&mut renderer, &mut world, &camera, RenderMode::PathTraced),
+        1
+    );
+    renderer.environment.fog = true;
+    assert_eq!(
+        render(&mut renderer, &mut world, &camera, RenderMode::PathTraced),
+        1
+    );
+    world.set_voxel(cgmath::vec4(0, 1, 0, 0), Voxel::new(0));
+    assert_eq!(
+        render(&mut renderer, &mut world, &camera, RenderMode::PathTraced),
+        1
+    );
+    assert_eq!(
+        render(&mut renderer, &mut world, &camera, RenderMode::PathTraced),
+        2
+    );
+
+    // the other modes don't accumulate, and switching back starts over
+    assert_eq!(
+        render(&mut renderer, &mut world, &camera, RenderMode::Perspective),
+        0
+    );
+    assert_eq!(
+        render(&mut renderer, &mut world, &camera, RenderMode::PathTraced),
+        1
+    );
+}
+
+#[test]
+fn open_floor_is_lit_by_the_sky_and_sun() {
+    let Some((device, queue)) = create_device() else {
+        eprintln!("skipping, no wgpu adapter is available");
+        return;
+    };
+
+    // nothing blocks the sky, so every diffuse bounce escapes to it and every sample is the same
+    let mut renderer = Renderer::new(&device);
+    renderer.lighting = Lighting {
+        sun_direction: cgmath::vec4(0.0, 1.0, 0.0, 0.0),
+        sun_intensity: 0.5,
+        ..Lighting::default()
+    };
+    renderer.environment = uniform_sky(0.25);
+    let color = render(&device, &queue, &mut renderer, &mut floor_world(None), 4);
+    assert_channels_close(color, 0.8 * 0.75, 2.0 / 255.0);
+
+    // without any bounces the sky can't be seen from the floor
+    renderer.lighting.max_bounces = 0;
+    let color = render(&device, &queue, &mut renderer, &mut floor_world(None), 4);
+    assert_channels_close(color, 0.8 * 0.5, 2.0 / 255.0);
+}
+
+#[test]
+fn emissive_ceiling_lights_the_floor() {
+    let Some((device, queue)) = create_device() else {
+        eprintln!("skipping, no wgpu adapter is available");
+        return;
+    };
+
+    let mut renderer = Renderer::new(&device);
+    // one bounce from the floor to the ceiling, which would otherwise keep reflecting light back and forth
+    renderer.lighting = Lighting {
+        sun_intensity: 0.0,
+        ambient: 0.0,
+        max_bounces: 1,
+        ..Lighting::default()
+    };
+    renderer.environment = uniform_sky(0.0);
+    // the ceiling covers most of what the floor can see, with the last of the light going out past its edges
+    let color = render(
+        &device,
+        &queue,
+        &mut renderer,
+        &mut floor_world(Some(1.0)),
+        32,
+    );
+    assert!(
+        (0..3).all(|i| color[i] > 0.6 && color[i] <= 0.8),
+        "expected most of 0.8, got {color:?}"
+    );
+
+    renderer.lighting.max_bounces = 0;
+    let color = render(
+        &device,
+        &queue,
+        &mut renderer,
+        &mut floor_world(Some(1.0)),
+        4,
+    );
+    assert_channels_close(color, 0.0, 0.0);
+}
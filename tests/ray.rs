@@ -0,0 +1,257 @@
+use cgmath::InnerSpace;
+use rand::{Rng, SeedableRng};
+use tesseracts::{
+    get_intersection, get_intersection_through, get_intersection_unaccelerated, Ray,
+    TerrainGenerator, TerrainMaterials, Voxel, World,
+};
+
+fn world_with(positions: &[cgmath::Vector4<i32>]) -> World {
+    let mut world = World::new();
+    for &position in positions {
+        world.set_voxel(position, Voxel::new(0));
+    }
+    world
+}
+
+#[test]
+fn axis_aligned_positive() {
+    let world = world_with(&[cgmath::vec4(3, 0, 0, 0)]);
+    let hit = get_intersection(
+        &world,
+        Ray {
+            origin: cgmath::vec4(0.5, 0.5, 0.5, 0.5),
+            direction: cgmath::vec4(1.0, 0.0, 0.0, 0.0),
+        },
+        100.0,
+    )
+    .unwrap();
+    assert_eq!(hit.voxel_position, cgmath::vec4(3, 0, 0, 0));
+    assert_eq!(hit.distance, 2.5);
+    assert_eq!(hit.position, cgmath::vec4(3.0, 0.5, 0.5, 0.5));
+    assert_eq!(hit.normal, cgmath::vec4(-1.0, 0.0, 0.0, 0.0));
+}
+
+#[test]
+fn axis_aligned_negative_across_chunks() {
+    let world = world_with(&[cgmath::vec4(0, 0, 0, -2)]);
+    let hit = get_intersection(
+        &world,
+        Ray {
+            origin: cgmath::vec4(0.5, 0.5, 0.5, 5.5),
+            direction: cgmath::vec4(0.0, 0.0, 0.0, -1.0),
+        },
+        100.0,
+    )
+    .unwrap();
+    assert_eq!(hit.voxel_position, cgmath::vec4(0, 0, 0, -2));
+    assert_eq!(hit.distance, 6.5);
+    assert_eq!(hit.normal, cgmath::vec4(0.0, 0.0, 0.0, 1.0));
+}
+
+/// The ray from the center of the screen of the default camera,
+/// zero direction components used to produce NaNs that made the traversal step along the wrong axis
+/// (the "black line through the middle of the screen")
+#[test]
+fn zero_direction_components_on_cell_boundary() {
+    let world = world_with(&[cgmath::vec4(0, 0, 0, 0)]);
+    let hit = get_intersection(
+        &world,
+        Ray {
+            origin: cgmath::vec4(0.0, 0.0, -3.0, 0.0),
+            direction: cgmath::vec4(0.0, 0.0, 1.0, 0.0),
+        },
+        100.0,
+    )
+    .unwrap();
+    assert_eq!(hit.voxel_position, cgmath::vec4(0, 0, 0, 0));
+    assert_eq!(hit.distance, 3.0);
+    assert_eq!(hit.normal, cgmath::vec4(0.0, 0.0, -1.0, 0.0));
+}
+
+#[test]
+fn zero_direction_never_hits() {
+    let world = world_with(&[cgmath::vec4(0, 0, 0, 0)]);
+    let hit = get_intersection(
+        &world,
+        Ray {
+            origin: cgmath::vec4(0.5, 0.5, 0.5, 0.5),
+            direction: cgmath::vec4(0.0, 0.0, 0.0, 0.0),
+        },
+        100.0,
+    );
+    assert_eq!(hit, None);
+}
+
+#[test]
+fn diagonal() {
+    let world = world_with(&[cgmath::vec4(2, 2, 2, 2)]);
+    let hit = get_intersection(
+        &world,
+        Ray {
+            origin: cgmath::vec4(0.25, 0.5, 0.5, 0.5),
+            direction: cgmath::vec4(1.0, 1.0, 1.0, 1.0).normalize(),
+        },
+        100.0,
+    )
+    .unwrap();
+    assert_eq!(hit.voxel_position, cgmath::vec4(2, 2, 2, 2));
+    // x is the last axis to cross into the voxel
+    assert_eq!(hit.normal, cgmath::vec4(-1.0, 0.0, 0.0, 0.0));
+    assert!((hit.distance - 3.5).abs() < 1e-5);
+    assert!((hit.position - cgmath::vec4(2.0, 2.25, 2.25, 2.25)).magnitude() < 1e-5);
+}
+
+/// The distance is in voxels rather than multiples of the direction, and the position agrees with it
+#[test]
+fn non_unit_direction() {
+    let world = world_with(&[cgmath::vec4(2, 2, 2, 2)]);
+    let unit = cgmath::vec4(1.0, 1.0, 1.0, 1.0).normalize();
+    let ray = |direction| Ray {
+        origin: cgmath::vec4(0.25, 0.5, 0.5, 0.5),
+        direction,
+    };
+    let expected = get_intersection(&world, ray(unit), 100.0).unwrap();
+    for scale in [0.25, 3.0] {
+        let hit = get_intersection(&world, ray(unit * scale), 100.0).unwrap();
+        assert_eq!(hit.voxel_position, expected.voxel_position);
+        assert_eq!(hit.normal, expected.normal);
+        assert!((hit.distance - expected.distance).abs() < 1e-5);
+        assert!((hit.position - expected.position).magnitude() < 1e-5);
+    }
+}
+
+#[test]
+fn origin_voxel_is_skipped() {
+    let world = world_with(&[cgmath::vec4(0, 0, 0, 0), cgmath::vec4(0, 1, 0, 0)]);
+    let hit = get_intersection(
+        &world,
+        Ray {
+            origin: cgmath::vec4(0.5, 0.5, 0.5, 0.5),
+            direction: cgmath::vec4(0.0, 1.0, 0.0, 0.0),
+        },
+        100.0,
+    )
+    .unwrap();
+    assert_eq!(hit.voxel_position, cgmath::vec4(0, 1, 0, 0));
+}
+
+#[test]
+fn through_a_medium() {
+    let mut world = world_with(&[cgmath::vec4(3, 0, 0, 0)]);
+    let glass = Voxel::new(1);
+    for x in 0..3 {
+        world.set_voxel(cgmath::vec4(x, 0, 0, 0), glass);
+    }
+    let ray = |direction| Ray {
+        origin: cgmath::vec4(1.5, 0.5, 0.5, 0.5),
+        direction,
+    };
+
+    // voxels of the medium are passed through, and whatever is next stops the ray
+    let hit = get_intersection_through(&world, ray(cgmath::vec4(1.0, 0.0, 0.0, 0.0)), 100.0, glass)
+        .unwrap();
+    assert_eq!(hit.voxel_position, cgmath::vec4(3, 0, 0, 0));
+    assert_eq!(hit.voxel, Voxel::new(0));
+
+    // including empty voxels, with the normal pointing back into the medium
+    let hit =
+        get_intersection_through(&world, ray(cgmath::vec4(-1.0, 0.0, 0.0, 0.0)), 100.0, glass)
+            .unwrap();
+    assert_eq!(hit.voxel_position, cgmath::vec4(-1, 0, 0, 0));
+    assert_eq!(hit.voxel, Voxel::EMPTY);
+    assert_eq!(hit.normal, cgmath::vec4(1.0, 0.0, 0.0, 0.0));
+    let hit = get_intersection_through(&world, ray(cgmath::vec4(0.0, 0.0, 0.0, 1.0)), 100.0, glass)
+        .unwrap();
+    assert_eq!(hit.voxel_position, cgmath::vec4(1, 0, 0, 1));
+    assert_eq!(hit.voxel, Voxel::EMPTY);
+
+    // the same as `get_intersection` through empty space
+    assert_eq!(
+        get_intersection_through(
+            &world,
+            ray(cgmath::vec4(0.0, 1.0, 0.0, 0.0)),
+            100.0,
+            Voxel::EMPTY
+        ),
+        get_intersection(&world, ray(cgmath::vec4(0.0, 1.0, 0.0, 0.0)), 100.0),
+    );
+}
+
+#[test]
+fn max_distance() {
+    let world = world_with(&[cgmath::vec4(10, 0, 0, 0)]);
+    let ray = Ray {
+        origin: cgmath::vec4(0.5, 0.5, 0.5, 0.5),
+        direction: cgmath::vec4(1.0, 0.0, 0.0, 0.0),
+    };
+    assert_eq!(get_intersection(&world, ray, 5.0), None);
+    assert!(get_intersection(&world, ray, 20.0).is_some());
+}
+
+#[test]
+fn skips_far_across_empty_space() {
+    let world = world_with(&[cgmath::vec4(-1000, 3, 0, 0), cgmath::vec4(1000, 3, 0, 0)]);
+    let hit = get_intersection(
+        &world,
+        Ray {
+            origin: cgmath::vec4(-999.5, 3.5, 0.5, 0.5),
+            direction: cgmath::vec4(1.0, 0.0, 0.0, 0.0),
+        },
+        10000.0,
+    )
+    .unwrap();
+    assert_eq!(hit.voxel_position, cgmath::vec4(1000, 3, 0, 0));
+    assert_eq!(hit.distance, 1999.5);
+    assert_eq!(hit.normal, cgmath::vec4(-1.0, 0.0, 0.0, 0.0));
+}
+
+#[test]
+fn skipping_matches_unaccelerated() {
+    let mut world = World::new();
+    let generator = TerrainGenerator::new(11, TerrainMaterials::add_to(&mut world));
+    generator.fill(
+        &mut world,
+        cgmath::vec4(-4, -4, -4, -1),
+        cgmath::vec4(3, 3, 3, 0),
+    );
+    let mut rng = rand::rngs::StdRng::seed_from_u64(0);
+    for _ in 0..200 {
+        world.set_voxel(
+            cgmath::vec4(
+                rng.gen_range(-100..100),
+                rng.gen_range(-100..100),
+                rng.gen_range(-100..100),
+                rng.gen_range(-100..100),
+            ),
+            Voxel::new(0),
+        );
+    }
+
+    for _ in 0..2000 {
+        let ray = Ray {
+            origin: cgmath::vec4(
+                rng.gen_range(-60.0..60.0),
+                rng.gen_range(-60.0..60.0),
+                rng.gen_range(-60.0..60.0),
+                rng.gen_range(-60.0..60.0),
+            ),
+            direction: cgmath::vec4(
+                rng.gen_range(-1.0..1.0),
+                rng.gen_range(-1.0..1.0),
+                rng.gen_range(-1.0..1.0),
+                rng.gen_range(-1.0f32..1.0),
+            )
+            .normalize(),
+        };
+        let expected = get_intersection_unaccelerated(&world, ray, 200.0);
+        let actual = get_intersection(&world, ray, 200.0);
+        assert_eq!(
+            actual.map(|hit| (hit.voxel_position, hit.normal)),
+            expected.map(|hit| (hit.voxel_position, hit.normal)),
+            "{ray:?}"
+        );
+        if let (Some(actual), Some(expected)) = (actual, expected) {
+            assert!((actual.distance - expected.distance).abs() < 1e-3);
+        }
+    }
+}
@@ -0,0 +1,106 @@
+use cgmath::InnerSpace;
+use tesseracts::{Camera, FovAxis, Material, ProjectionMode, Scene, Voxel, World};
+
+fn assert_same_world(a: &World, b: &World) {
+    assert_eq!(a.materials(), b.materials());
+    assert_eq!(a.chunks().count(), b.chunks().count());
+    for (position, chunk) in a.chunks() {
+        assert_eq!(b.chunk(position).unwrap().data, chunk.data);
+    }
+}
+
+#[test]
+fn example_scene_matches_example_world() {
+    let scene = Scene::load(concat!(env!("CARGO_MANIFEST_DIR"), "/scenes/example.ron")).unwrap();
+    assert_same_world(&scene.world().unwrap(), &World::example());
+    let camera = scene.camera.camera();
+    let default = Camera::default();
+    assert_eq!(camera.position, default.position);
+    assert_eq!(camera.orientation, default.orientation);
+    assert_eq!(camera.projection, default.projection);
+}
+
+#[test]
+fn boxes_and_clears() {
+    let scene = Scene::parse(
+        r#"
+        Scene(
+            materials: [(name: "stone", color: (0.5, 0.5, 0.5)), (name: "dirt", color: (0.4, 0.3, 0.2))],
+            regions: [
+                Box(min: (-2, -1, -2, -2), max: (1, 0, 1, 1), material: "stone"),
+                Box(min: (-2, 0, -2, -2), max: (1, 0, 1, 1), material: "dirt"),
+                Clear(min: (0, 0, 0, 0), max: (0, 0, 0, 0)),
+                Voxel(position: (5, 5, 5, -5), material: "dirt"),
+            ],
+        )
+        "#,
+    )
+    .unwrap();
+    let world = scene.world().unwrap();
+
+    let stone = Voxel::new(0);
+    let dirt = Voxel::new(1);
+    assert_eq!(world.get_voxel(cgmath::vec4(-2, -1, -2, -2)), stone);
+    assert_eq!(world.get_voxel(cgmath::vec4(1, -1, 1, 1)), stone);
+    assert_eq!(world.get_voxel(cgmath::vec4(1, 0, -2, 1)), dirt);
+    assert!(world.get_voxel(cgmath::vec4(0, 0, 0, 0)).is_empty());
+    assert!(world.get_voxel(cgmath::vec4(2, -1, 0, 0)).is_empty());
+    assert!(world.get_voxel(cgmath::vec4(0, 1, 0, 0)).is_empty());
+    assert_eq!(world.get_voxel(cgmath::vec4(5, 5, 5, -5)), dirt);
+
+    let filled = world
+        .chunks()
+        .flat_map(|(_, chunk)| chunk.data.iter())
+        .filter(|voxel| !voxel.is_empty())
+        .count();
+    // the stone and dirt box, minus the cleared voxel, plus the lone dirt voxel
+    assert_eq!(filled, 4 * 2 * 4 * 4 - 1 + 1);
+}
+
+#[test]
+fn camera() {
+    let scene = Scene::parse(
+        r#"
+        Scene(
+            camera: (
+                position: (1.0, 2.0, 3.0, 4.0),
+                rotations: [(XZ, 90.0)],
+                projection: Orthographic,
+                fov_axis: Horizontal,
+                orthographic_size: 8.0,
+            ),
+        )
+        "#,
+    )
+    .unwrap();
+    let camera = scene.camera.camera();
+    assert_eq!(camera.position, cgmath::vec4(1.0, 2.0, 3.0, 4.0));
+    // a positive XZ rotation turns forward towards right
+    assert!((camera.forward() - cgmath::vec4(1.0, 0.0, 0.0, 0.0)).magnitude() < 1e-5);
+    assert_eq!(camera.projection.mode, ProjectionMode::Orthographic);
+    assert_eq!(camera.projection.fov_axis, FovAxis::Horizontal);
+    assert_eq!(camera.projection.orthographic_size, 8.0);
+    assert_eq!(camera.projection.fov, Camera::default().projection.fov);
+}
+
+#[test]
+fn errors() {
+    assert!(Scene::parse("Scene(regions: [Sphere()])").is_err());
+    let unknown_material =
+        Scene::parse(r#"Scene(regions: [Voxel(position: (0, 0, 0, 0), material: "missing")])"#)
+            .unwrap();
+    assert!(unknown_material.world().is_err());
+}
+
+#[test]
+fn material_properties() {
+    let scene = Scene::load(concat!(env!("CARGO_MANIFEST_DIR"), "/scenes/materials.ron")).unwrap();
+    let world = scene.world().unwrap();
+    let [stone, mirror, glass, lamp, _] = world.materials() else {
+        panic!("expected 5 materials");
+    };
+    assert_eq!(*stone, Material::new(cgmath::vec3(0.6, 0.6, 0.6)));
+    assert_eq!(mirror.reflectivity, 0.85);
+    assert_eq!((glass.transparency, glass.refractive_index), (0.9, 1.5));
+    assert_eq!(lamp.emission, 1.5);
+}
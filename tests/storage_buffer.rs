@@ -0,0 +1,38 @@
+mod common;
+
+use common::{create_device, read_buffer};
+use eframe::wgpu;
+use tesseracts::StorageBuffer;
+
+#[test]
+fn grow_keeps_contents() {
+    let Some((device, queue)) = create_device() else {
+        eprintln!("skipping, no wgpu adapter is available");
+        return;
+    };
+
+    let mut buffer = StorageBuffer::new(
+        &device,
+        wgpu::BufferDescriptor {
+            label: Some("Test Storage Buffer"),
+            size: 8,
+            usage: wgpu::BufferUsages::COPY_SRC
+                | wgpu::BufferUsages::COPY_DST
+                | wgpu::BufferUsages::STORAGE,
+            mapped_at_creation: false,
+        },
+    );
+    buffer.write_range(&queue, 0, &[1, 2, 3, 4, 5, 6, 7, 8]);
+    // a write that is still queued when the buffer grows
+    buffer.write_range(&queue, 4, &[9, 9, 9, 9]);
+
+    assert!(buffer.grow(&device, &queue, 16));
+    assert!(!buffer.grow(&device, &queue, 12));
+    assert_eq!(buffer.buffer_descriptor.size, 16);
+    buffer.write_range(&queue, 12, &[10, 11, 12, 13]);
+
+    assert_eq!(
+        read_buffer(&device, &queue, &buffer),
+        [1, 2, 3, 4, 9, 9, 9, 9, 0, 0, 0, 0, 10, 11, 12, 13]
+    );
+}
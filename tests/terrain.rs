@@ -0,0 +1,158 @@
+use tesseracts::{Biome, Chunk, TerrainGenerator, TerrainMaterials, Voxel, World, CHUNK_SIZE};
+
+fn generator(seed: u64) -> (World, TerrainGenerator) {
+    let mut world = World::new();
+    let materials = TerrainMaterials::add_to(&mut world);
+    (world, TerrainGenerator::new(seed, materials))
+}
+
+/// Chunks from below the deepest surface to above the highest one
+fn chunk_range() -> (cgmath::Vector4<i32>, cgmath::Vector4<i32>) {
+    (cgmath::vec4(-3, -4, -3, -1), cgmath::vec4(2, 3, 2, 0))
+}
+
+#[test]
+fn deterministic() {
+    let (_, a) = generator(42);
+    let (_, b) = generator(42);
+    let (_, c) = generator(43);
+    let mut differs = false;
+    for x in -8..8 {
+        for w in -8..8 {
+            let position = cgmath::vec4(x, 0, x * 3, w);
+            assert_eq!(
+                a.generate_chunk(position).data,
+                b.generate_chunk(position).data
+            );
+            differs |= a.height(x * 5, x * 3, w * 7) != c.height(x * 5, x * 3, w * 7);
+        }
+    }
+    assert!(differs, "different seeds produced the same terrain");
+}
+
+#[test]
+fn seeds_keep_their_terrain() {
+    // recorded from an earlier run, so that worlds shared by seed stay the same across dependency updates
+    let (_, generator) = generator(42);
+    let heights = (0..8)
+        .map(|i| generator.height(i * 13 - 40, i * 7 - 20, i * 29 - 100))
+        .collect::<Vec<_>>();
+    assert_eq!(heights, [4, 0, -1, 4, 3, 0, -3, -4]);
+}
+
+#[test]
+fn chunks_match_voxels() {
+    let (_, generator) = generator(7);
+    let size = CHUNK_SIZE as i32;
+    for chunk_position in [
+        cgmath::vec4(0, 0, 0, 0),
+        cgmath::vec4(-1, -1, 2, -3),
+        cgmath::vec4(3, -2, -5, 1),
+    ] {
+        let chunk = generator.generate_chunk(chunk_position);
+        for index in 0..CHUNK_SIZE.pow(4) {
+            let local = cgmath::vec4(
+                index % CHUNK_SIZE,
+                index / CHUNK_SIZE % CHUNK_SIZE,
+                index / (CHUNK_SIZE * CHUNK_SIZE) % CHUNK_SIZE,
+                index / (CHUNK_SIZE * CHUNK_SIZE * CHUNK_SIZE),
+            );
+            let position = chunk_position * size + local.cast().unwrap();
+            assert_eq!(chunk.data[Chunk::index(local)], generator.voxel(position));
+        }
+    }
+}
+
+#[test]
+fn columns() {
+    let (_, generator) = generator(3);
+    let materials = generator.materials;
+    for x in -20..20 {
+        for w in -3..3 {
+            let z = x * 2 - 5;
+            let height = generator.height(x, z, w);
+            let surface = generator.voxel(cgmath::vec4(x, height, z, w));
+            let expected_surface = match generator.biome(x, z, w) {
+                Biome::Grassland => materials.grass,
+                Biome::Desert => materials.sand,
+                Biome::Mountains => materials.snow,
+            };
+            assert_eq!(surface.material, expected_surface);
+            assert!(generator
+                .voxel(cgmath::vec4(x, height + 1, z, w))
+                .is_empty());
+            for depth in 0..generator.cave_depth {
+                assert!(!generator
+                    .voxel(cgmath::vec4(x, height - depth, z, w))
+                    .is_empty());
+            }
+        }
+    }
+}
+
+#[test]
+fn biomes() {
+    let (_, generator) = generator(1);
+    let mut seen = Vec::new();
+    for x in (-512..512).step_by(8) {
+        for w in (-512..512).step_by(64) {
+            let biome = generator.biome(x, x / 2, w);
+            if !seen.contains(&biome) {
+                seen.push(biome);
+            }
+        }
+    }
+    assert_eq!(seen.len(), 3, "only found {seen:?}");
+}
+
+#[test]
+fn caves() {
+    let (_, mut generator) = generator(5);
+    let count_underground_empty = |generator: &TerrainGenerator| {
+        let mut count = 0;
+        for x in -16..16 {
+            for z in -16..16 {
+                let height = generator.height(x, z, 0);
+                for y in height - 24..height - generator.cave_depth {
+                    count += generator.voxel(cgmath::vec4(x, y, z, 0)).is_empty() as usize;
+                }
+            }
+        }
+        count
+    };
+    assert!(count_underground_empty(&generator) > 0);
+    generator.cave_threshold = 1.0;
+    assert_eq!(count_underground_empty(&generator), 0);
+}
+
+#[test]
+fn fill() {
+    let (mut world, generator) = generator(9);
+    let (min, max) = chunk_range();
+    generator.fill(&mut world, min, max);
+
+    assert!(world.chunks().all(|(_, chunk)| !chunk.is_empty()));
+    let size = CHUNK_SIZE as i32;
+    for x in min.x * size..(max.x + 1) * size {
+        for w in min.w * size..(max.w + 1) * size {
+            let z = -x - 1;
+            let height = generator.height(x, z, w);
+            for y in height - 3..height + 3 {
+                let position = cgmath::vec4(x, y, z, w);
+                assert_eq!(world.get_voxel(position), generator.voxel(position));
+            }
+        }
+    }
+
+    // filling again replaces edits
+    world.set_voxel(cgmath::vec4(0, 100, 0, 0), Voxel::new(0));
+    let height = generator.height(0, 0, 0);
+    world.set_voxel(cgmath::vec4(0, height, 0, 0), Voxel::EMPTY);
+    generator.fill(
+        &mut world,
+        cgmath::vec4(0, -4, 0, 0),
+        cgmath::vec4(0, 25, 0, 0),
+    );
+    assert!(world.get_voxel(cgmath::vec4(0, 100, 0, 0)).is_empty());
+    assert!(!world.get_voxel(cgmath::vec4(0, height, 0, 0)).is_empty());
+}
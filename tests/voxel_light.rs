@@ -0,0 +1,195 @@
+use tesseracts::{load_world, save_world, Chunk, Material, Voxel, World, MAX_LIGHT_LEVEL};
+
+/// Three empty chunks in a row along x, from x = -4 to 7, with materials for a lamp and a wall
+fn corridor() -> (World, u32, u32) {
+    let mut world = World::new();
+    for x in -1..=1 {
+        world.insert_chunk(cgmath::vec4(x, 0, 0, 0), Chunk::new());
+    }
+    let lamp = world.add_material(Material {
+        emission: 1.0,
+        ..Material::new(cgmath::vec3(1.0, 1.0, 0.5))
+    });
+    let wall = world.add_material(Material::new(cgmath::vec3(0.5, 0.5, 0.5)));
+    (world, lamp, wall)
+}
+
+fn light(world: &World, position: cgmath::Vector4<i32>) -> u32 {
+    world.get_voxel(position).light
+}
+
+/// Checks that every voxel has the same light as in `expected`, which was built from scratch
+fn assert_same_light(world: &World, expected: &World) {
+    for (chunk_position, chunk) in expected.chunks() {
+        let actual = world.chunk(chunk_position).unwrap();
+        for (index, (a, b)) in actual.data.iter().zip(&chunk.data).enumerate() {
+            assert_eq!(a, b, "voxel {index} of chunk {chunk_position:?}");
+        }
+    }
+}
+
+#[test]
+fn light_spreads_through_empty_voxels() {
+    let (mut world, lamp, _) = corridor();
+    world.set_voxel(cgmath::vec4(0, 1, 1, 1), Voxel::new(lamp));
+
+    assert_eq!(light(&world, cgmath::vec4(0, 1, 1, 1)), MAX_LIGHT_LEVEL);
+    // one level less for each step along any of the 4 axes, across chunks too
+    for offset in [
+        cgmath::vec4(1, 0, 0, 0),
+        cgmath::vec4(-1, 0, 0, 0),
+        cgmath::vec4(0, 1, 0, 0),
+        cgmath::vec4(0, -1, 0, 0),
+        cgmath::vec4(0, 0, 1, 0),
+        cgmath::vec4(0, 0, -1, 0),
+        cgmath::vec4(0, 0, 0, 1),
+        cgmath::vec4(0, 0, 0, -1),
+    ] {
+        assert_eq!(light(&world, cgmath::vec4(0, 1, 1, 1) + offset), 14);
+    }
+    assert_eq!(light(&world, cgmath::vec4(2, 2, 0, 1)), 11);
+    assert_eq!(light(&world, cgmath::vec4(7, 1, 1, 1)), 8);
+    assert_eq!(light(&world, cgmath::vec4(-4, 3, 3, 3)), 5);
+    // there is nowhere to store light outside the chunks
+    assert_eq!(light(&world, cgmath::vec4(0, 4, 1, 1)), 0);
+}
+
+#[test]
+fn emission_sets_the_light_level() {
+    let (mut world, lamp, _) = corridor();
+    world.set_voxel(cgmath::vec4(0, 1, 1, 1), Voxel::new(lamp));
+
+    let mut dim = world.materials()[lamp as usize];
+    dim.emission = 0.5;
+    world.set_material(lamp, dim);
+    assert_eq!(light(&world, cgmath::vec4(0, 1, 1, 1)), 8);
+    assert_eq!(light(&world, cgmath::vec4(3, 1, 1, 1)), 5);
+    assert_eq!(light(&world, cgmath::vec4(7, 1, 1, 1)), 1);
+
+    dim.emission = 0.0;
+    world.set_material(lamp, dim);
+    assert!(world
+        .chunks()
+        .all(|(_, chunk)| chunk.data.iter().all(|voxel| voxel.light == 0)));
+
+    // emission above 1 is as bright as it gets
+    dim.emission = 3.0;
+    world.set_material(lamp, dim);
+    assert_eq!(light(&world, cgmath::vec4(0, 1, 1, 1)), MAX_LIGHT_LEVEL);
+}
+
+#[test]
+fn solid_voxels_block_light() {
+    let (mut world, lamp, wall) = corridor();
+    world.set_voxel(cgmath::vec4(0, 1, 1, 1), Voxel::new(lamp));
+    for y in 0..4 {
+        for z in 0..4 {
+            for w in 0..4 {
+                world.set_voxel(cgmath::vec4(2, y, z, w), Voxel::new(wall));
+            }
+        }
+    }
+    assert_eq!(light(&world, cgmath::vec4(1, 1, 1, 1)), 14);
+    assert_eq!(light(&world, cgmath::vec4(2, 1, 1, 1)), 0);
+    assert_eq!(light(&world, cgmath::vec4(3, 1, 1, 1)), 0);
+    assert_eq!(light(&world, cgmath::vec4(7, 1, 1, 1)), 0);
+
+    // a hole in the wall lets the light back through
+    world.set_voxel(cgmath::vec4(2, 1, 1, 1), Voxel::EMPTY);
+    assert_eq!(light(&world, cgmath::vec4(2, 1, 1, 1)), 13);
+    assert_eq!(light(&world, cgmath::vec4(3, 1, 1, 1)), 12);
+    assert_eq!(light(&world, cgmath::vec4(3, 0, 1, 1)), 11);
+
+    // and filling it in takes it away again
+    world.set_voxel(cgmath::vec4(2, 1, 1, 1), Voxel::new(wall));
+    assert_eq!(light(&world, cgmath::vec4(3, 1, 1, 1)), 0);
+}
+
+#[test]
+fn edits_match_lighting_from_scratch() {
+    let (mut world, lamp, wall) = corridor();
+    world.set_voxel(cgmath::vec4(-3, 0, 0, 0), Voxel::new(lamp));
+    world.set_voxel(cgmath::vec4(5, 3, 2, 1), Voxel::new(lamp));
+    world.set_voxel(cgmath::vec4(1, 1, 1, 1), Voxel::new(lamp));
+    for y in 0..4 {
+        world.set_voxel(cgmath::vec4(3, y, 2, 1), Voxel::new(wall));
+    }
+    // the light of the removed lamp is taken away, but not where the other lamps still reach
+    world.set_voxel(cgmath::vec4(1, 1, 1, 1), Voxel::EMPTY);
+    world.set_voxel(cgmath::vec4(3, 2, 2, 1), Voxel::EMPTY);
+
+    let (mut expected, _, _) = corridor();
+    expected.set_voxel(cgmath::vec4(-3, 0, 0, 0), Voxel::new(lamp));
+    expected.set_voxel(cgmath::vec4(5, 3, 2, 1), Voxel::new(lamp));
+    for y in [0, 1, 3] {
+        expected.set_voxel(cgmath::vec4(3, y, 2, 1), Voxel::new(wall));
+    }
+    assert_same_light(&world, &expected);
+    assert_ne!(light(&world, cgmath::vec4(1, 1, 1, 1)), 0);
+
+    world.set_voxel(cgmath::vec4(-3, 0, 0, 0), Voxel::EMPTY);
+    world.set_voxel(cgmath::vec4(5, 3, 2, 1), Voxel::EMPTY);
+    assert!(world
+        .chunks()
+        .all(|(_, chunk)| chunk.data.iter().all(|voxel| voxel.light == 0)));
+}
+
+#[test]
+fn chunks_take_their_light_with_them() {
+    let (mut world, lamp, _) = corridor();
+    world.set_voxel(cgmath::vec4(3, 1, 1, 1), Voxel::new(lamp));
+    let (mut lit, _, _) = corridor();
+    lit.set_voxel(cgmath::vec4(3, 1, 1, 1), Voxel::new(lamp));
+
+    // removing an unlit chunk next to the lamp only darkens that chunk
+    let removed = world.remove_chunk(cgmath::vec4(1, 0, 0, 0)).unwrap();
+    assert_eq!(light(&world, cgmath::vec4(3, 1, 1, 1)), MAX_LIGHT_LEVEL);
+    assert_eq!(light(&world, cgmath::vec4(4, 1, 1, 1)), 0);
+
+    // and putting it back lights it up again, whatever light it had stored
+    let mut stale = removed;
+    stale.data.fill(Voxel {
+        light: MAX_LIGHT_LEVEL,
+        ..Voxel::EMPTY
+    });
+    world.insert_chunk(cgmath::vec4(1, 0, 0, 0), stale);
+    assert_same_light(&world, &lit);
+
+    // the chunk with the lamp in it
+    let removed = world.remove_chunk(cgmath::vec4(0, 0, 0, 0)).unwrap();
+    assert!(world
+        .chunks()
+        .all(|(_, chunk)| chunk.data.iter().all(|voxel| voxel.light == 0)));
+    world.insert_chunk(cgmath::vec4(0, 0, 0, 0), removed);
+    assert_same_light(&world, &lit);
+
+    // replacing a chunk takes away the light of the old one
+    world.insert_chunk(cgmath::vec4(0, 0, 0, 0), Chunk::new());
+    assert!(world
+        .chunks()
+        .all(|(_, chunk)| chunk.data.iter().all(|voxel| voxel.light == 0)));
+}
+
+#[test]
+fn world_files_leave_out_light() {
+    let (mut world, lamp, wall) = corridor();
+    world.set_voxel(cgmath::vec4(0, 1, 1, 1), Voxel::new(lamp));
+    // empty chunks aren't saved
+    world.set_voxel(cgmath::vec4(-4, 3, 3, 3), Voxel::new(wall));
+    world.set_voxel(cgmath::vec4(7, 3, 3, 3), Voxel::new(wall));
+    let mut lit = Vec::new();
+    save_world(&mut lit, &world).unwrap();
+
+    // without light, runs of empty voxels aren't broken up by it, so the file is no bigger
+    let mut dark = load_world(&mut lit.as_slice()).unwrap();
+    let mut unlit = dark.materials()[lamp as usize];
+    unlit.emission = 0.0;
+    dark.set_material(lamp, unlit);
+    let mut bytes = Vec::new();
+    save_world(&mut bytes, &dark).unwrap();
+    assert_eq!(lit.len(), bytes.len());
+
+    // the light is worked out again when loading
+    let loaded = load_world(&mut lit.as_slice()).unwrap();
+    assert_same_light(&loaded, &world);
+}
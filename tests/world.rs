@@ -0,0 +1,111 @@
+use tesseracts::{
+    Chunk, ChunkChanges, ChunkOccupancy, Voxel, World, CHUNK_SIZE, CHUNK_VOLUME, OCCUPANCY_LEVELS,
+};
+
+fn take_ranges(world: &mut World) -> Vec<(cgmath::Vector4<i32>, std::ops::Range<usize>)> {
+    match world.take_chunk_changes() {
+        ChunkChanges::All => panic!("expected individual chunks"),
+        ChunkChanges::Chunks(chunks) => {
+            let mut chunks = chunks.into_iter().collect::<Vec<_>>();
+            chunks.sort_by_key(|(position, _)| (position.w, position.z, position.y, position.x));
+            chunks
+        }
+    }
+}
+
+#[test]
+fn chunk_changes() {
+    let mut world = World::new();
+    assert!(matches!(world.take_chunk_changes(), ChunkChanges::All));
+    assert!(take_ranges(&mut world).is_empty());
+
+    // a single voxel only covers its own index
+    let index = Chunk::index(cgmath::vec4(1, 2, 3, 0));
+    world.set_voxel(cgmath::vec4(1, 2, 3, 0), Voxel::new(0));
+    assert_eq!(
+        take_ranges(&mut world),
+        [(cgmath::vec4(0, 0, 0, 0), index..index + 1)]
+    );
+
+    // setting a voxel to what it already is isn't a change
+    world.set_voxel(cgmath::vec4(1, 2, 3, 0), Voxel::new(0));
+    assert!(take_ranges(&mut world).is_empty());
+
+    // several voxels in one chunk are merged into the range between them
+    let first = Chunk::index(cgmath::vec4(0, 1, 0, 0));
+    let last = Chunk::index(cgmath::vec4(0, 0, 0, 1));
+    let other = Chunk::index(cgmath::vec4(CHUNK_SIZE - 1, 0, 0, 0));
+    world.set_voxel(cgmath::vec4(0, 0, 0, 1), Voxel::new(0));
+    world.set_voxel(cgmath::vec4(0, 1, 0, 0), Voxel::new(0));
+    world.set_voxel(cgmath::vec4(-1, 0, 0, 0), Voxel::new(0));
+    assert_eq!(
+        take_ranges(&mut world),
+        [
+            (cgmath::vec4(-1, 0, 0, 0), other..other + 1),
+            (cgmath::vec4(0, 0, 0, 0), first..last + 1),
+        ]
+    );
+
+    // inserting and removing whole chunks covers all of them
+    world.set_voxel(cgmath::vec4(0, 0, 0, 0), Voxel::EMPTY);
+    world.insert_chunk(cgmath::vec4(0, 0, 0, 0), Chunk::new());
+    world.remove_chunk(cgmath::vec4(-1, 0, 0, 0));
+    assert_eq!(
+        take_ranges(&mut world),
+        [
+            (cgmath::vec4(-1, 0, 0, 0), 0..CHUNK_VOLUME),
+            (cgmath::vec4(0, 0, 0, 0), 0..CHUNK_VOLUME),
+        ]
+    );
+}
+
+#[test]
+fn occupancy() {
+    let mut world = World::new();
+    let occupied_levels = |world: &World, chunk_position: cgmath::Vector4<i32>| {
+        (1..=OCCUPANCY_LEVELS)
+            .filter(|&level| {
+                world
+                    .occupancy()
+                    .is_occupied(level, ChunkOccupancy::cell(chunk_position, level))
+            })
+            .count()
+    };
+
+    world.set_voxel(cgmath::vec4(-1, 0, 0, 0), Voxel::new(0));
+    world.insert_chunk(cgmath::vec4(1, 0, 0, 0), Chunk::new());
+    // replacing a chunk doesn't count it twice
+    world.insert_chunk(cgmath::vec4(1, 0, 0, 0), Chunk::new());
+    assert_eq!(
+        occupied_levels(&world, cgmath::vec4(-1, 0, 0, 0)),
+        OCCUPANCY_LEVELS
+    );
+    assert_eq!(
+        occupied_levels(&world, cgmath::vec4(0, 0, 0, 0)),
+        OCCUPANCY_LEVELS
+    );
+    // the cell at level 1 is 2 chunks across, and negative positions round down
+    assert_eq!(
+        occupied_levels(&world, cgmath::vec4(-2, 0, 0, 0)),
+        OCCUPANCY_LEVELS
+    );
+    assert_eq!(
+        occupied_levels(&world, cgmath::vec4(-3, 0, 0, 0)),
+        OCCUPANCY_LEVELS - 1
+    );
+    assert_eq!(
+        occupied_levels(&world, cgmath::vec4(2, 0, 0, 0)),
+        OCCUPANCY_LEVELS - 1
+    );
+    assert_eq!(occupied_levels(&world, cgmath::vec4(0, 0, 1000, 0)), 0);
+
+    // cells are aligned to 0, so chunks on either side of it never share one
+    world.remove_chunk(cgmath::vec4(1, 0, 0, 0));
+    assert_eq!(occupied_levels(&world, cgmath::vec4(0, 0, 0, 0)), 0);
+    assert_eq!(
+        occupied_levels(&world, cgmath::vec4(-5, 0, 0, 0)),
+        OCCUPANCY_LEVELS - 2
+    );
+    world.remove_chunk(cgmath::vec4(-1, 0, 0, 0));
+    assert_eq!(occupied_levels(&world, cgmath::vec4(-1, 0, 0, 0)), 0);
+}
@@ -0,0 +1,247 @@
+use std::io;
+
+use tesseracts::{load_world, save_world, Material, Voxel, World, WORLD_FILE_MAGIC};
+
+fn round_trip(world: &World) -> World {
+    let mut bytes = Vec::new();
+    save_world(&mut bytes, world).unwrap();
+    load_world(&mut bytes.as_slice()).unwrap()
+}
+
+fn header(chunk_size: u32, voxel_record_size: u32, material_record_size: u32) -> Vec<u8> {
+    let mut bytes = WORLD_FILE_MAGIC.to_vec();
+    for value in [1, chunk_size, voxel_record_size, material_record_size] {
+        bytes.extend_from_slice(&u32::to_le_bytes(value));
+    }
+    bytes
+}
+
+fn push_u32(bytes: &mut Vec<u8>, value: u32) {
+    bytes.extend_from_slice(&value.to_le_bytes());
+}
+
+#[test]
+fn round_trip_example() {
+    let world = World::example();
+    let loaded = round_trip(&world);
+    assert_eq!(loaded.materials(), world.materials());
+    for (position, chunk) in world.chunks() {
+        assert_eq!(loaded.chunk(position).unwrap().data, chunk.data);
+    }
+    assert_eq!(loaded.chunks().count(), world.chunks().count());
+}
+
+#[test]
+fn round_trip_negative_and_sparse() {
+    let mut world = World::new();
+    let material = world.add_material(Material {
+        reflectivity: 0.25,
+        transparency: 0.5,
+        refractive_index: 1.33,
+        emission: 2.0,
+        ..Material::new(cgmath::vec3(0.25, 0.5, 0.75))
+    });
+    let positions = [
+        cgmath::vec4(-1, -1, -1, -1),
+        cgmath::vec4(-9, 3, 100, -4),
+        cgmath::vec4(7, 0, -5, 2),
+    ];
+    for position in positions {
+        world.set_voxel(position, Voxel::new(material));
+    }
+    // chunks that have been emptied are not saved
+    world.set_voxel(cgmath::vec4(40, 40, 40, 40), Voxel::new(material));
+    world.set_voxel(cgmath::vec4(40, 40, 40, 40), Voxel::EMPTY);
+
+    let loaded = round_trip(&world);
+    assert_eq!(loaded.materials(), world.materials());
+    assert_eq!(loaded.chunks().count(), positions.len());
+    for position in positions {
+        // the material is emissive, so the voxels light themselves up again
+        assert_eq!(loaded.get_voxel(position), world.get_voxel(position));
+        assert_eq!(loaded.get_voxel(position).material, material);
+    }
+    assert!(loaded.get_voxel(cgmath::vec4(0, 0, 0, 0)).is_empty());
+}
+
+#[test]
+fn different_chunk_size() {
+    // a single chunk with a size of 2 at (-1, 0, 0, 0), holding one voxel at its local (1, 0, 0, 1)
+    let mut bytes = header(2, 4, 12);
+    push_u32(&mut bytes, 1);
+    bytes.extend_from_slice(&[0; 12]);
+    push_u32(&mut bytes, 1);
+    for component in [-1, 0, 0, 0] {
+        bytes.extend_from_slice(&i32::to_le_bytes(component));
+    }
+    // x + w * 2^3
+    let index = 1 + 8;
+    let mut data = Vec::new();
+    push_u32(&mut data, index);
+    push_u32(&mut data, u32::MAX);
+    push_u32(&mut data, 1);
+    push_u32(&mut data, 0);
+    push_u32(&mut data, 16 - index - 1);
+    push_u32(&mut data, u32::MAX);
+    push_u32(&mut bytes, data.len() as u32);
+    bytes.extend_from_slice(&data);
+
+    let world = load_world(&mut bytes.as_slice()).unwrap();
+    assert_eq!(world.get_voxel(cgmath::vec4(-1, 0, 0, 1)), Voxel::new(0));
+    assert_eq!(
+        world
+            .chunks()
+            .flat_map(|(_, chunk)| chunk.data.iter())
+            .filter(|voxel| !voxel.is_empty())
+            .count(),
+        1
+    );
+}
+
+#[test]
+fn different_record_sizes() {
+    // materials with an eighth field from the future, and voxels with a second one
+    let mut bytes = header(4, 8, 32);
+    push_u32(&mut bytes, 1);
+    for field in [0.5f32, 0.25, 1.0, 0.5, 0.0, 1.5, 3.0, 123.0] {
+        bytes.extend_from_slice(&field.to_le_bytes());
+    }
+    push_u32(&mut bytes, 1);
+    for component in [0, 0, 0, 0] {
+        bytes.extend_from_slice(&i32::to_le_bytes(component));
+    }
+    let mut data = Vec::new();
+    push_u32(&mut data, 256);
+    push_u32(&mut data, 0);
+    push_u32(&mut data, 42);
+    push_u32(&mut bytes, data.len() as u32);
+    bytes.extend_from_slice(&data);
+
+    let world = load_world(&mut bytes.as_slice()).unwrap();
+    assert_eq!(
+        world.materials(),
+        &[Material {
+            reflectivity: 0.5,
+            emission: 3.0,
+            ..Material::new(cgmath::vec3(0.5, 0.25, 1.0))
+        }]
+    );
+    assert_eq!(world.get_voxel(cgmath::vec4(3, 3, 3, 3)).material, 0);
+}
+
+#[test]
+fn materials_from_before_reflections() {
+    // only the color was saved, so everything else is the default
+    let mut bytes = header(4, 4, 12);
+    push_u32(&mut bytes, 1);
+    for component in [0.5f32, 0.25, 1.0] {
+        bytes.extend_from_slice(&component.to_le_bytes());
+    }
+    push_u32(&mut bytes, 0);
+
+    let world = load_world(&mut bytes.as_slice()).unwrap();
+    assert_eq!(
+        world.materials(),
+        &[Material::new(cgmath::vec3(0.5, 0.25, 1.0))]
+    );
+}
+
+#[test]
+fn rejects_invalid_files() {
+    assert!(load_world(&mut &b"not a world file"[..]).is_err());
+
+    let mut newer = WORLD_FILE_MAGIC.to_vec();
+    push_u32(&mut newer, u32::MAX);
+    assert!(load_world(&mut newer.as_slice()).is_err());
+
+    // a chunk that claims more voxels than it holds
+    let mut overfull = header(4, 4, 12);
+    push_u32(&mut overfull, 0);
+    push_u32(&mut overfull, 1);
+    overfull.extend_from_slice(&[0; 16]);
+    push_u32(&mut overfull, 8);
+    push_u32(&mut overfull, 257);
+    push_u32(&mut overfull, 0);
+    assert!(load_world(&mut overfull.as_slice()).is_err());
+
+    let mut bytes = Vec::new();
+    save_world(&mut bytes, &World::example()).unwrap();
+    bytes.truncate(bytes.len() - 1);
+    assert!(load_world(&mut bytes.as_slice()).is_err());
+}
+
+/// A file with one material and a single chunk at `position`, filled with `material`
+fn single_chunk(chunk_size: u32, position: [i32; 4], material: u32) -> Vec<u8> {
+    let mut bytes = header(chunk_size, 4, 12);
+    push_u32(&mut bytes, 1);
+    bytes.extend_from_slice(&[0; 12]);
+    push_u32(&mut bytes, 1);
+    for component in position {
+        bytes.extend_from_slice(&i32::to_le_bytes(component));
+    }
+    push_u32(&mut bytes, 8);
+    push_u32(&mut bytes, chunk_size.pow(4));
+    push_u32(&mut bytes, material);
+    bytes
+}
+
+fn assert_invalid_data(bytes: &[u8]) {
+    let error = load_world(&mut &bytes[..])
+        .err()
+        .expect("loaded a corrupt file");
+    assert_eq!(error.kind(), io::ErrorKind::InvalidData, "{error}");
+}
+
+#[test]
+fn rejects_oversized_records() {
+    // the sizes are checked before anything that large is read, so there's nothing after the header
+    assert_invalid_data(&header(4, 17, 12));
+    assert_invalid_data(&header(4, u32::MAX, 12));
+    assert_invalid_data(&header(4, 4, 113));
+    assert_invalid_data(&header(4, 4, u32::MAX));
+    assert_invalid_data(&header(17, 4, 12));
+    assert_invalid_data(&header(u32::MAX, 4, 12));
+
+    // records too small to hold the fields every version saved, which would otherwise read nothing,
+    // leaving the counts alone to decide how much is loaded
+    assert_invalid_data(&header(4, 0, 12));
+    assert_invalid_data(&header(4, 3, 12));
+    let mut bytes = header(4, 4, 0);
+    push_u32(&mut bytes, 3_000_000);
+    assert_invalid_data(&bytes);
+    assert_invalid_data(&header(4, 4, 11));
+
+    // counts beyond what any world needs are rejected before anything is read for them
+    let mut bytes = header(4, 4, 12);
+    push_u32(&mut bytes, u32::MAX);
+    assert_invalid_data(&bytes);
+    let mut bytes = header(4, 4, 12);
+    push_u32(&mut bytes, 0);
+    push_u32(&mut bytes, u32::MAX);
+    assert_invalid_data(&bytes);
+
+    // up to 4 times the current sizes leaves room for fields added later
+    let mut bytes = header(4, 16, 112);
+    push_u32(&mut bytes, 0);
+    push_u32(&mut bytes, 0);
+    assert!(load_world(&mut bytes.as_slice()).is_ok());
+}
+
+#[test]
+fn rejects_unknown_materials() {
+    assert!(load_world(&mut single_chunk(4, [0, 0, 0, 0], 0).as_slice()).is_ok());
+    assert!(load_world(&mut single_chunk(4, [0, 0, 0, 0], u32::MAX).as_slice()).is_ok());
+    assert_invalid_data(&single_chunk(4, [0, 0, 0, 0], 1));
+    assert_invalid_data(&single_chunk(2, [0, 0, 0, 0], 1000));
+}
+
+#[test]
+fn rejects_chunks_out_of_range() {
+    // the voxels of chunks of another size have to fit within `i32` once they're split up
+    let last = i32::MAX / 2;
+    assert!(load_world(&mut single_chunk(2, [last, 0, 0, -last - 1], 0).as_slice()).is_ok());
+    assert_invalid_data(&single_chunk(2, [last + 1, 0, 0, 0], 0));
+    assert_invalid_data(&single_chunk(2, [0, i32::MIN, 0, 0], 0));
+    assert_invalid_data(&single_chunk(3, [0, 0, i32::MAX / 3, 0], 0));
+    assert_invalid_data(&single_chunk(4, [0, 0, 0, i32::MAX], 0));
+}